@@ -1,11 +1,17 @@
 #[macro_use]
 extern crate criterion;
 
+use catalyst_common::batch;
 use catalyst_common::constants;
 use catalyst_common::keys;
 use catalyst_common::std_signature;
+use catalyst_common::{
+    Message, Signature, SignatureBatch, SignatureBatchView, SigningKey, VerificationKeyCache,
+    VerifyingKey,
+};
 use criterion::black_box;
-use criterion::Criterion;
+use criterion::{BenchmarkId, Criterion, Throughput};
+use rand::rngs::OsRng;
 
 pub fn sign_benchmark(c: &mut Criterion) {
     let mut sig = [0u8; constants::SIGNATURE_LENGTH];
@@ -41,7 +47,7 @@ pub fn verify_benchmark(c: &mut Criterion) {
     c.bench_function("verify ed25519ph", |b| {
         b.iter(|| {
             std_signature::verify(
-                black_box(&mut sig),
+                black_box(&sig),
                 black_box(&public_key),
                 black_box(message),
                 black_box(context),
@@ -50,5 +56,242 @@ pub fn verify_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benchmarks, sign_benchmark, verify_benchmark);
+pub fn signing_key_sign_benchmark(c: &mut Criterion) {
+    let signing_key = SigningKey::from_bytes(&[0u8; constants::PRIVATE_KEY_LENGTH]).unwrap();
+
+    let message = b"Message 1 2 3";
+    let context = b"Context 1 2 3";
+    c.bench_function("sign ed25519ph with SigningKey", |b| {
+        b.iter(|| signing_key.sign(black_box(message), black_box(context)))
+    });
+}
+
+pub fn verifying_key_verify_benchmark(c: &mut Criterion) {
+    let signing_key = SigningKey::from_bytes(&[0u8; constants::PRIVATE_KEY_LENGTH]).unwrap();
+    let verifying_key: VerifyingKey = *signing_key.verifying_key();
+
+    let message = b"Message 1 2 3";
+    let context = b"Context 1 2 3";
+    let signature: Signature = signing_key.sign(message, context).unwrap();
+
+    c.bench_function("verify ed25519ph with VerifyingKey", |b| {
+        b.iter(|| {
+            verifying_key.verify(
+                black_box(message),
+                black_box(context),
+                black_box(&signature),
+            )
+        })
+    });
+}
+
+fn signed_batch(size: usize) -> SignatureBatch {
+    let context = b"Context 1 2 3";
+    let mut batch_sigs = SignatureBatch::new();
+    batch_sigs.set_context(context.to_vec());
+    for i in 0..size {
+        let signing_key = SigningKey::generate(&mut OsRng {});
+        let message = format!("Message {}", i).into_bytes();
+        let signature = signing_key.sign(&message, context).unwrap();
+        batch_sigs.mut_messages().push(message);
+        batch_sigs
+            .mut_signatures()
+            .push(signature.to_bytes().to_vec());
+        batch_sigs
+            .mut_public_keys()
+            .push(signing_key.verifying_key().to_bytes().to_vec());
+    }
+    batch_sigs
+}
+
+pub fn batch_verify_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch verify ed25519ph");
+    group.sample_size(10);
+    for size in [16, 256, 4096, 32768].iter() {
+        let batch_sigs = signed_batch(*size);
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &batch_sigs,
+            |b, batch_sigs| {
+                b.iter_with_setup(
+                    || batch_sigs.clone(),
+                    |mut batch_sigs| batch::try_verify_batch(&mut batch_sigs, &mut OsRng {}),
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+pub fn verify_with_cache_benchmark(c: &mut Criterion) {
+    let signing_key = SigningKey::from_bytes(&[0u8; constants::PRIVATE_KEY_LENGTH]).unwrap();
+    let public_key = signing_key.verifying_key().to_bytes();
+    let message = b"Message 1 2 3";
+    let context = b"Context 1 2 3";
+    let signature = signing_key.sign(message, context).unwrap().to_bytes();
+
+    let mut group = c.benchmark_group("verify ed25519ph key cache");
+    group.bench_function("cold", |b| {
+        b.iter(|| {
+            std_signature::try_verify(
+                black_box(&signature),
+                black_box(&public_key),
+                black_box(message),
+                black_box(context),
+            )
+        })
+    });
+    let mut cache = VerificationKeyCache::new(16);
+    group.bench_function("warm", |b| {
+        b.iter(|| {
+            std_signature::try_verify_with_cache(
+                black_box(&signature),
+                black_box(&public_key),
+                black_box(message),
+                black_box(context),
+                &mut cache,
+            )
+        })
+    });
+    group.finish();
+}
+
+pub fn batch_verify_with_cache_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch verify ed25519ph key cache");
+    group.sample_size(10);
+    for size in [32, 256].iter() {
+        let batch_sigs = signed_batch(*size);
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("cold", size),
+            &batch_sigs,
+            |b, batch_sigs| {
+                b.iter_with_setup(
+                    || batch_sigs.clone(),
+                    |mut batch_sigs| batch::try_verify_batch(&mut batch_sigs, &mut OsRng {}),
+                )
+            },
+        );
+        let mut cache = VerificationKeyCache::new(1024);
+        group.bench_with_input(
+            BenchmarkId::new("warm", size),
+            &batch_sigs,
+            |b, batch_sigs| {
+                b.iter_with_setup(
+                    || batch_sigs.clone(),
+                    |mut batch_sigs| {
+                        batch::try_verify_batch_with_cache(
+                            &mut batch_sigs,
+                            &mut cache,
+                            &mut OsRng {},
+                        )
+                    },
+                )
+            },
+        );
+        let mut cache = VerificationKeyCache::new(1024);
+        cache.set_validators(batch_sigs.get_public_keys()).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("validator tables", size),
+            &batch_sigs,
+            |b, batch_sigs| {
+                b.iter_with_setup(
+                    || batch_sigs.clone(),
+                    |mut batch_sigs| {
+                        batch::try_verify_batch_with_cache(
+                            &mut batch_sigs,
+                            &mut cache,
+                            &mut OsRng {},
+                        )
+                    },
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+pub fn single_signer_batch_verify_benchmark(c: &mut Criterion) {
+    let context = b"Context 1 2 3";
+    let signing_key = SigningKey::generate(&mut OsRng {});
+    let messages: Vec<Vec<u8>> = (0..256)
+        .map(|i| format!("Message {}", i).into_bytes())
+        .collect();
+    let signatures: Vec<Signature> = messages
+        .iter()
+        .map(|message| signing_key.sign(message, context).unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("batch verify ed25519ph single signer");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(256));
+    group.bench_function("256", |b| {
+        b.iter(|| {
+            batch::verify_single_signer(
+                signing_key.verifying_key(),
+                black_box(&messages),
+                black_box(&signatures),
+                context,
+                &mut OsRng {},
+            )
+        })
+    });
+    group.finish();
+}
+
+pub fn sign_batch_benchmark(c: &mut Criterion) {
+    let context = b"Context 1 2 3";
+    let signing_key = SigningKey::generate(&mut OsRng {});
+    let messages: Vec<Vec<u8>> = (0..256)
+        .map(|i| format!("Message {}", i).into_bytes())
+        .collect();
+
+    let mut group = c.benchmark_group("sign ed25519ph batch");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(256));
+    group.bench_function("256", |b| {
+        b.iter(|| batch::sign_signature_batch(&[&signing_key], black_box(&messages), context))
+    });
+    group.finish();
+}
+
+pub fn decode_batch_benchmark(c: &mut Criterion) {
+    let context = b"Context 1 2 3";
+    let signing_key = SigningKey::generate(&mut OsRng {});
+    let mut group = c.benchmark_group("decode signature batch");
+    for size in [256, 4096].iter() {
+        let messages: Vec<Vec<u8>> = (0..*size).map(|i| vec![i as u8; 1024]).collect();
+        let bytes = batch::sign_signature_batch(&[&signing_key], &messages, context)
+            .unwrap()
+            .write_to_bytes()
+            .unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::new("copied", size), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut batch_sigs = SignatureBatch::new();
+                batch_sigs.merge_from_bytes(black_box(bytes)).unwrap();
+                batch_sigs
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("borrowed", size), &bytes, |b, bytes| {
+            b.iter(|| SignatureBatchView::from_bytes(black_box(bytes)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benchmarks,
+    sign_benchmark,
+    verify_benchmark,
+    signing_key_sign_benchmark,
+    verifying_key_verify_benchmark,
+    batch_verify_benchmark,
+    verify_with_cache_benchmark,
+    batch_verify_with_cache_benchmark,
+    single_signer_batch_verify_benchmark,
+    sign_batch_benchmark,
+    decode_batch_benchmark
+);
 criterion_main!(benchmarks);
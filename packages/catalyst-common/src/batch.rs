@@ -1,134 +1,1179 @@
 use super::*;
 
-use core::iter::once;
+use core::iter::{empty, once};
+use core::ops::Range;
+use std::collections::hash_map::{Entry, HashMap};
 
-use crate::extensions::{PublicKeyExt, SignatureExposed};
-use curve25519_dalek::constants;
-use curve25519_dalek::edwards::EdwardsPoint;
+use crate::cache::{ValidatorTable, VerificationKeyCache};
+use crate::signature::{challenge, domain_hash, equation_holds, prehashed_challenge};
+use crate::wire::SignatureBatchView;
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::IsIdentity;
-use curve25519_dalek::traits::VartimeMultiscalarMul;
-use rand::Rng;
+use curve25519_dalek::traits::{VartimeMultiscalarMul, VartimePrecomputedMultiscalarMul};
 
-use std::slice;
-
-use catalyst_protocol_sdk_rust::Cryptography::{ErrorCode, SignatureBatch};
-use ed25519_dalek::{Digest, Sha512, Signature};
+use catalyst_protocol_sdk_rust::Cryptography::SignatureBatch;
+use ed25519_dalek::{Digest, Sha512};
 use rand::{CryptoRng, RngCore};
 
+/// The parts of one signature which take part in the verification equation
+/// `[s]B = R + [k]A`, with `k = H(dom || R || A || M')` already computed.
 #[allow(non_snake_case)]
-fn verify_batch_unwrapped<T>(
-    messages: &[Vec<u8>],
-    sigs: &[SignatureExposed],
-    public_keys: &[PublicKey],
-    context: Option<&'static [u8]>,
-    csprng: &mut T,
-) -> i32
-where
-    T: CryptoRng + RngCore,
-{
+struct PreparedEntry {
+    R: CompressedEdwardsY,
+    s: Scalar,
+    A: VerifyingKey,
+    k: Scalar,
+}
+
+impl PreparedEntry {
+    /// Checks this entry on its own, exactly as `VerifyingKey::verify` would.
+    fn verify_single(&self) -> bool {
+        equation_holds(&self.R, &self.s, self.A.point(), &self.k)
+    }
+}
+
+/// Hashes every (signature, public key, message) triplet under the ed25519ph
+/// domain separator for `context`.
+fn prepare_entries<M: AsRef<[u8]>>(
+    messages: &[M],
+    sigs: &[Signature],
+    public_keys: &[VerifyingKey],
+    context: Option<&[u8]>,
+) -> Result<Vec<PreparedEntry>, CryptoError> {
     // Return an error code if any of the vectors are not the same size as the others.
     if sigs.len() != messages.len()
         || sigs.len() != public_keys.len()
         || public_keys.len() != messages.len()
     {
-        return ErrorCode::ARRAYS_NOT_EQUAL_LENGTH.value();
+        return Err(CryptoError::ArraysNotEqualLength);
     }
 
     let ctx: &[u8] = context.unwrap_or(b"");
-    debug_assert!(
-        ctx.len() <= 255,
-        "The context must not be longer than 255 octets."
-    );
+    SignatureMode::Ed25519ph.check_context(ctx)?;
 
-    let mut common_hash: Sha512 = Sha512::new();
-    common_hash.input(b"SigEd25519 no Ed25519 collisions");
-    common_hash.input(&[1]); // Ed25519ph
-    common_hash.input(&[ctx.len() as u8]);
-    common_hash.input(ctx);
+    let common_hash: Sha512 = domain_hash(SignatureMode::Ed25519ph, ctx);
 
     // Compute H(dom || R || A || H(M)) for each (signature, public_key, message) triplet
-    let hrams: Vec<Scalar> = (0..messages.len())
+    Ok((0..messages.len())
         .map(|i| {
             let mut h: Sha512 = common_hash.clone();
             h.input(sigs[i].R.as_bytes());
             h.input(public_keys[i].as_bytes());
-            h.input(Sha512::digest(&messages[i]).as_slice());
-            Scalar::from_hash(h)
+            h.input(Sha512::digest(messages[i].as_ref()).as_slice());
+            PreparedEntry {
+                R: sigs[i].R,
+                s: sigs[i].s,
+                A: public_keys[i],
+                k: Scalar::from_hash(h),
+            }
         })
-        .collect();
+        .collect())
+}
 
-    // Select a random 128-bit scalar for each signature.
-    let zs: Vec<Scalar> = sigs
-        .iter()
-        .map(|_| Scalar::from(csprng.gen::<u128>()))
-        .collect();
+/// Domain separator for the transcript from which `BatchRandomness::Transcript`
+/// derives its scalars.
+const TRANSCRIPT_LABEL: &[u8] = b"Catalyst batch verification transcript";
+
+/// How the 128-bit scalars `z`, by which each signature of a batch is
+/// multiplied before they are summed, are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchRandomness<'a> {
+    /// Each `z` is drawn from the generator passed in.
+    #[default]
+    Random,
+    /// Each `z` is derived from a hash of every `R`, `A`, `s` and `k` of the
+    /// batch, mixed with the given extra entropy, which may be empty. As `k`
+    /// commits to the context and the message hash, the transcript covers all
+    /// that the verification equation depends on, so a forger cannot learn
+    /// any `z` without fixing every signature first. The generator passed in
+    /// is not used, and the same batch always gets the same result.
+    Transcript(&'a [u8]),
+}
+
+impl<'a> BatchRandomness<'a> {
+    /// Selects the randomness by its number across the FFI: 0 for `Random`,
+    /// and 1 for `Transcript` with `extra_entropy`.
+    pub fn from_i32(value: i32, extra_entropy: &'a [u8]) -> Option<Self> {
+        match value {
+            0 => Some(BatchRandomness::Random),
+            1 => Some(BatchRandomness::Transcript(extra_entropy)),
+            _ => None,
+        }
+    }
+}
+
+/// Chooses the scalar `z` of each entry according to `randomness`, failing
+/// with `CryptoError::Unknown` if `csprng` cannot supply the bytes.
+fn coefficients<T>(
+    entries: &[PreparedEntry],
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<Vec<Scalar>, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    let extra_entropy = match randomness {
+        BatchRandomness::Random => {
+            return entries
+                .iter()
+                .map(|_| {
+                    let mut z = [0u8; 16];
+                    csprng
+                        .try_fill_bytes(&mut z)
+                        .map_err(|_| CryptoError::Unknown)?;
+                    Ok(Scalar::from(u128::from_le_bytes(z)))
+                })
+                .collect()
+        }
+        BatchRandomness::Transcript(extra_entropy) => extra_entropy,
+    };
+
+    let mut transcript = Sha512::new();
+    transcript.input(TRANSCRIPT_LABEL);
+    transcript.input((extra_entropy.len() as u64).to_le_bytes());
+    transcript.input(extra_entropy);
+    transcript.input((entries.len() as u64).to_le_bytes());
+    for entry in entries {
+        transcript.input(entry.R.as_bytes());
+        transcript.input(entry.A.as_bytes());
+        transcript.input(entry.s.as_bytes());
+        transcript.input(entry.k.as_bytes());
+    }
+    let seed = transcript.result();
+
+    Ok((0..entries.len() as u64)
+        .map(|i| {
+            let hash = Sha512::new().chain(seed).chain(i.to_le_bytes()).result();
+            let mut z = [0u8; 16];
+            z.copy_from_slice(&hash[..16]);
+            Scalar::from(u128::from_le_bytes(z))
+        })
+        .collect())
+}
+
+/// Checks all of `entries` with a single multiscalar multiplication, taking
+/// the basepoint and any validator keys from the precomputed `validators`.
+#[allow(non_snake_case)]
+fn verify_entries<T>(
+    entries: &[PreparedEntry],
+    validators: Option<&ValidatorTable>,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<bool, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    // Select a 128-bit scalar for each signature.
+    let zs = coefficients(entries, randomness, csprng)?;
 
     // Compute the basepoint coefficient, ∑ s[i]z[i] (mod l)
-    let B_coefficient: Scalar = sigs
+    let B_coefficient: Scalar = entries
         .iter()
-        .map(|sig| sig.s)
+        .map(|entry| entry.s)
         .zip(zs.iter())
         .map(|(s, z)| z * s)
         .sum();
 
     // Multiply each H(dom || R || A || H(M)) by the random value
-    let zhrams = hrams.iter().zip(zs.iter()).map(|(hram, z)| hram * z);
+    let zks = entries.iter().zip(zs.iter()).map(|(entry, z)| entry.k * z);
 
-    let Rs = sigs.iter().map(|sig| sig.R.decompress());
-    let As = public_keys
-        .iter()
-        .map(|pk| Some(pk.to_decompressed_point()));
-    let B = once(Some(constants::ED25519_BASEPOINT_POINT));
+    // Signatures by the same key share a single A[j], whose coefficient is
+    // the sum of theirs, so n signatures by k signers need only n + k points
+    // besides B. Keys with precomputed tables are summed separately.
+    let mut static_scalars = validators.map(|table| vec![Scalar::zero(); table.static_len()]);
+    let mut signers: HashMap<&[u8; constants::PUBLIC_KEY_LENGTH], usize> = HashMap::new();
+    let mut A_coefficients: Vec<Scalar> = Vec::new();
+    let mut As: Vec<Option<EdwardsPoint>> = Vec::new();
+    for (entry, zk) in entries.iter().zip(zks) {
+        let static_index = validators.and_then(|table| table.static_index(&entry.A));
+        if let (Some(i), Some(static_scalars)) = (static_index, static_scalars.as_mut()) {
+            static_scalars[i] += zk;
+            continue;
+        }
+        match signers.entry(entry.A.as_bytes()) {
+            Entry::Occupied(signer) => A_coefficients[*signer.get()] += zk,
+            Entry::Vacant(signer) => {
+                signer.insert(As.len());
+                A_coefficients.push(zk);
+                As.push(Some(*entry.A.point()));
+            }
+        }
+    }
 
-    // Compute (-∑ z[i]s[i] (mod l)) B + ∑ z[i]R[i] + ∑ (z[i]H(dom || R || A || H(M))[i] (mod l)) A[i] = 0
-    let id = EdwardsPoint::optional_multiscalar_mul(
-        once(-B_coefficient).chain(zs.iter().cloned()).chain(zhrams),
-        B.chain(Rs).chain(As),
+    let Rs = entries.iter().map(|entry| entry.R.decompress());
+
+    // Compute [8]((-∑ z[i]s[i] (mod l)) B + ∑ z[i]R[i] + ∑ (∑ z[i]H(dom || R || A || H(M))[i] (mod l)) A[j]) = 0
+    let id = match (validators, static_scalars) {
+        (Some(table), Some(mut static_scalars)) => {
+            // The basepoint and validator keys use the precomputed tables,
+            // while the other points are left to the size-dependent algorithm
+            // of `optional_multiscalar_mul`.
+            static_scalars[0] = -B_coefficient;
+            let static_part = table.precomputation.vartime_mixed_multiscalar_mul(
+                static_scalars,
+                empty::<Scalar>(),
+                empty::<EdwardsPoint>(),
+            );
+            EdwardsPoint::optional_multiscalar_mul(
+                zs.iter().cloned().chain(A_coefficients),
+                Rs.chain(As),
+            )
+            .map(|dynamic_part| static_part + dynamic_part)
+        }
+        _ => EdwardsPoint::optional_multiscalar_mul(
+            once(-B_coefficient)
+                .chain(zs.iter().cloned())
+                .chain(A_coefficients),
+            once(Some(ED25519_BASEPOINT_POINT)).chain(Rs).chain(As),
+        ),
+    };
+    Ok(matches!(id, Some(id) if id.mul_by_cofactor().is_identity()))
+}
+
+/// Writes the validity of each of `entries` into `results`. A range which
+/// passes as a batch is accepted whole, otherwise it is split in half until
+/// single entries are left, which are then verified on their own.
+fn find_invalid_entries<T>(
+    entries: &[PreparedEntry],
+    results: &mut [bool],
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    debug_assert_eq!(entries.len(), results.len());
+    match entries.len() {
+        0 => {}
+        1 => results[0] = entries[0].verify_single(),
+        len => {
+            if verify_entries(entries, None, randomness, csprng)? {
+                results.iter_mut().for_each(|valid| *valid = true);
+                return Ok(());
+            }
+            let (left, right) = entries.split_at(len / 2);
+            let (left_results, right_results) = results.split_at_mut(len / 2);
+            find_invalid_entries(left, left_results, randomness, csprng)?;
+            find_invalid_entries(right, right_results, randomness, csprng)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prepares and verifies `len` entries in chunks of at most `chunk_size`, so
+/// that only one chunk's worth of hashes and points is held at a time. Each
+/// chunk is a batch of its own with independent scalars, so a transcript is
+/// taken of each chunk; with the `parallel` feature the chunks are spread
+/// across all cores, each drawing any randomness from a generator seeded by
+/// `csprng`.
+fn verify_in_chunks<F, T>(
+    len: usize,
+    chunk_size: usize,
+    prepare: F,
+    validators: Option<&ValidatorTable>,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    F: Fn(Range<usize>) -> Result<Vec<PreparedEntry>, CryptoError> + Sync,
+    T: CryptoRng + RngCore,
+{
+    let chunk_size = chunk_size.max(1);
+    let chunks = (0..len)
+        .step_by(chunk_size)
+        .map(|start| start..len.min(start + chunk_size));
+
+    #[cfg(feature = "parallel")]
+    {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use rayon::prelude::*;
+
+        let seeded = chunks
+            .map(|range| {
+                let mut seed = <StdRng as SeedableRng>::Seed::default();
+                if let BatchRandomness::Random = randomness {
+                    csprng
+                        .try_fill_bytes(&mut seed)
+                        .map_err(|_| CryptoError::Unknown)?;
+                }
+                Ok((range, seed))
+            })
+            .collect::<Result<Vec<_>, CryptoError>>()?;
+        seeded.into_par_iter().try_for_each(|(range, seed)| {
+            let entries = prepare(range)?;
+            let mut chunk_rng = StdRng::from_seed(seed);
+            if verify_entries(&entries, validators, randomness, &mut chunk_rng)? {
+                Ok(())
+            } else {
+                Err(CryptoError::BatchVerificationFailure)
+            }
+        })
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        for range in chunks {
+            let entries = prepare(range)?;
+            if !verify_entries(&entries, validators, randomness, csprng)? {
+                return Err(CryptoError::BatchVerificationFailure);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn verify_batch_unwrapped<M, T>(
+    messages: &[M],
+    sigs: &[Signature],
+    public_keys: &[VerifyingKey],
+    context: Option<&[u8]>,
+    chunk_size: usize,
+    validators: Option<&ValidatorTable>,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    M: AsRef<[u8]> + Sync,
+    T: CryptoRng + RngCore,
+{
+    // Check the whole batch up front so that every chunk is well formed.
+    if sigs.len() != messages.len() || sigs.len() != public_keys.len() {
+        return Err(CryptoError::ArraysNotEqualLength);
+    }
+    SignatureMode::Ed25519ph.check_context(context.unwrap_or(b""))?;
+
+    verify_in_chunks(
+        sigs.len(),
+        chunk_size,
+        |range| {
+            prepare_entries(
+                &messages[range.clone()],
+                &sigs[range.clone()],
+                &public_keys[range],
+                context,
+            )
+        },
+        validators,
+        randomness,
+        csprng,
     )
-    .ok_or_else(|| ErrorCode::BATCH_VERIFICATION_FAILURE.value())
-    .unwrap();
+}
 
-    if id.is_identity() {
-        ErrorCode::NO_ERROR.value()
-    } else {
-        ErrorCode::BATCH_VERIFICATION_FAILURE.value()
+/// Verifies Ed25519ph signatures of many messages by a single signer under a
+/// shared context, so that the signer's key enters each multiscalar
+/// multiplication once. `signatures[i]` must be the signature of `messages[i]`.
+pub fn verify_single_signer<M, T>(
+    public_key: &VerifyingKey,
+    messages: &[M],
+    signatures: &[Signature],
+    context: &[u8],
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    M: AsRef<[u8]> + Sync,
+    T: CryptoRng + RngCore,
+{
+    if signatures.is_empty() {
+        return Err(CryptoError::InvalidSignature);
     }
+    if signatures.len() != messages.len() {
+        return Err(CryptoError::ArraysNotEqualLength);
+    }
+    SignatureMode::Ed25519ph.check_context(context)?;
+
+    verify_in_chunks(
+        signatures.len(),
+        constants::BATCH_CHUNK_SIZE,
+        |range| {
+            let public_keys = vec![*public_key; range.len()];
+            prepare_entries(
+                &messages[range.clone()],
+                &signatures[range],
+                &public_keys,
+                Some(context),
+            )
+        },
+        None,
+        BatchRandomness::Random,
+        csprng,
+    )
+}
+
+pub fn verify_batch<T>(batch_sigs: &mut SignatureBatch, csprng: &mut T) -> i32
+where
+    T: CryptoRng + RngCore,
+{
+    error::to_error_code(try_verify_batch(batch_sigs, csprng))
+}
+
+/// Verifies every signature in the batch at once, consuming the signatures,
+/// public keys and context of `batch_sigs`. Batches larger than
+/// `constants::BATCH_CHUNK_SIZE` are verified in chunks of that size.
+pub fn try_verify_batch<T>(
+    batch_sigs: &mut SignatureBatch,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    try_verify_batch_chunked(batch_sigs, constants::BATCH_CHUNK_SIZE, csprng)
+}
+
+/// As `try_verify_batch`, verifying the batch in chunks of at most
+/// `chunk_size` signatures.
+pub fn try_verify_batch_chunked<T>(
+    batch_sigs: &mut SignatureBatch,
+    chunk_size: usize,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_signature_batch(
+        batch_sigs,
+        &VerificationPolicy::default(),
+        None,
+        chunk_size,
+        BatchRandomness::Random,
+        csprng,
+    )
+}
+
+pub fn verify_batch_with_policy<T>(
+    batch_sigs: &mut SignatureBatch,
+    policy: &VerificationPolicy,
+    csprng: &mut T,
+) -> i32
+where
+    T: CryptoRng + RngCore,
+{
+    error::to_error_code(try_verify_batch_with_policy(batch_sigs, policy, csprng))
+}
+
+/// As `try_verify_batch`, decoding and checking every signature and public
+/// key according to `policy`.
+pub fn try_verify_batch_with_policy<T>(
+    batch_sigs: &mut SignatureBatch,
+    policy: &VerificationPolicy,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_signature_batch(
+        batch_sigs,
+        policy,
+        None,
+        constants::BATCH_CHUNK_SIZE,
+        BatchRandomness::Random,
+        csprng,
+    )
+}
+
+pub fn verify_batch_with_cache<T>(
+    batch_sigs: &mut SignatureBatch,
+    cache: &mut VerificationKeyCache,
+    csprng: &mut T,
+) -> i32
+where
+    T: CryptoRng + RngCore,
+{
+    error::to_error_code(try_verify_batch_with_cache(batch_sigs, cache, csprng))
+}
+
+/// As `try_verify_batch`, taking decompressed public keys from `cache`, and
+/// using its precomputed tables for any keys of its validator set.
+pub fn try_verify_batch_with_cache<T>(
+    batch_sigs: &mut SignatureBatch,
+    cache: &mut VerificationKeyCache,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_signature_batch(
+        batch_sigs,
+        &VerificationPolicy::default(),
+        Some(cache),
+        constants::BATCH_CHUNK_SIZE,
+        BatchRandomness::Random,
+        csprng,
+    )
+}
+
+pub fn verify_batch_with_randomness<T>(
+    batch_sigs: &mut SignatureBatch,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> i32
+where
+    T: CryptoRng + RngCore,
+{
+    error::to_error_code(try_verify_batch_with_randomness(
+        batch_sigs, randomness, csprng,
+    ))
+}
+
+/// As `try_verify_batch`, choosing the scalars which weight each signature
+/// according to `randomness`.
+pub fn try_verify_batch_with_randomness<T>(
+    batch_sigs: &mut SignatureBatch,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_signature_batch(
+        batch_sigs,
+        &VerificationPolicy::default(),
+        None,
+        constants::BATCH_CHUNK_SIZE,
+        randomness,
+        csprng,
+    )
+}
+
+/// Verifies the batch, then clears its signatures, public keys and context
+/// as documented for the public functions.
+fn verify_signature_batch<T>(
+    batch_sigs: &mut SignatureBatch,
+    policy: &VerificationPolicy,
+    cache: Option<&mut VerificationKeyCache>,
+    chunk_size: usize,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    let result = verify_view(
+        &SignatureBatchView::from_batch(batch_sigs),
+        policy,
+        cache,
+        chunk_size,
+        randomness,
+        csprng,
+    );
+    batch_sigs.clear_signatures();
+    batch_sigs.clear_public_keys();
+    batch_sigs.clear_context();
+    result
+}
+
+/// Verifies every signature of a batch decoded by `SignatureBatchView`,
+/// decoding and checking each signature and public key according to `policy`
+/// and choosing the scalars which weight them according to `randomness`.
+/// Nothing is copied out of the view's buffer.
+pub fn try_verify_batch_view<T>(
+    view: &SignatureBatchView,
+    policy: &VerificationPolicy,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_view(
+        view,
+        policy,
+        None,
+        constants::BATCH_CHUNK_SIZE,
+        randomness,
+        csprng,
+    )
 }
 
-pub fn verify_batch<T>(batch_sigs: &mut SignatureBatch, mut csprng: &mut T) -> i32
+fn verify_view<T>(
+    view: &SignatureBatchView,
+    policy: &VerificationPolicy,
+    mut cache: Option<&mut VerificationKeyCache>,
+    chunk_size: usize,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
 where
     T: CryptoRng + RngCore,
 {
-    let sigs = batch_sigs
-        .take_signatures()
+    let sigs = view
+        .signatures()
         .iter()
-        .map(|x| Signature::from_bytes(&x).expect("not decoding sigs").into())
-        .collect::<Vec<SignatureExposed>>();
+        .map(|x| {
+            let sig = policy.decode_signature(x)?;
+            policy.check_signature(&sig)?;
+            Ok(sig)
+        })
+        .collect::<Result<Vec<Signature>, CryptoError>>()?;
     if sigs.is_empty() {
-        return ErrorCode::INVALID_SIGNATURE.value();
+        return Err(CryptoError::InvalidSignature);
     }
-    let pks = batch_sigs
-        .take_public_keys()
+    let pks = view
+        .public_keys()
         .iter()
-        .map(|x| PublicKey::from_bytes(&x).unwrap())
-        .collect::<Vec<PublicKey>>();
-
-    let context_vec = batch_sigs.take_context();
+        .map(|x| {
+            let pk = match cache.as_mut() {
+                Some(cache) => cache.get(x)?,
+                None => VerifyingKey::from_bytes(x)?,
+            };
+            policy.check_public_key(&pk)?;
+            Ok(pk)
+        })
+        .collect::<Result<Vec<VerifyingKey>, CryptoError>>()?;
 
-    let context = unsafe { slice::from_raw_parts(context_vec.as_ptr(), context_vec.len()) };
     verify_batch_unwrapped(
-        batch_sigs.messages.as_slice(),
+        view.messages(),
         sigs.as_slice(),
         pks.as_slice(),
-        Some(context),
-        &mut csprng,
+        Some(view.context()),
+        chunk_size,
+        cache.and_then(|cache| cache.validators()),
+        randomness,
+        csprng,
+    )
+}
+
+/// Verifies every signature in the batch and reports the validity of each
+/// entry, in the order of `batch_sigs.signatures`. Entries whose signature or
+/// public key cannot be decoded are reported as invalid; the batch as a whole
+/// is rejected only if its arrays differ in length or its context is too long.
+pub fn verify_batch_each<T>(
+    batch_sigs: &SignatureBatch,
+    csprng: &mut T,
+) -> Result<Vec<bool>, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_batch_view_each(&SignatureBatchView::from_batch(batch_sigs), csprng)
+}
+
+/// As `verify_batch_each`, for a batch decoded by `SignatureBatchView`.
+pub fn verify_batch_view_each<T>(
+    view: &SignatureBatchView,
+    csprng: &mut T,
+) -> Result<Vec<bool>, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_batch_arrays_each(
+        view.signatures(),
+        view.public_keys(),
+        view.messages(),
+        &[view.context()],
+        csprng,
+    )
+}
+
+/// Verifies Ed25519ph signatures given as parallel arrays of encodings, where
+/// `contexts` holds either one context shared by every entry or one context
+/// per entry. Fails as `try_verify_batch` does.
+pub fn try_verify_batch_arrays<T>(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    messages: &[&[u8]],
+    contexts: &[&[u8]],
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_arrays(signatures, public_keys, messages, contexts, false, csprng)
+}
+
+/// As `try_verify_batch_arrays`, given the SHA-512 digest of each message
+/// rather than the message itself.
+pub fn try_verify_batch_prehashed<T>(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    prehashes: &[[u8; constants::PREHASH_LENGTH]],
+    contexts: &[&[u8]],
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_arrays(signatures, public_keys, prehashes, contexts, true, csprng)
+}
+
+/// As `try_verify_batch_arrays`, reporting the validity of each entry as
+/// `verify_batch_each` does.
+pub fn verify_batch_arrays_each<T>(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    messages: &[&[u8]],
+    contexts: &[&[u8]],
+    csprng: &mut T,
+) -> Result<Vec<bool>, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_arrays_each(signatures, public_keys, messages, contexts, false, csprng)
+}
+
+/// As `try_verify_batch_prehashed`, reporting the validity of each entry as
+/// `verify_batch_each` does.
+pub fn verify_batch_prehashed_each<T>(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    prehashes: &[[u8; constants::PREHASH_LENGTH]],
+    contexts: &[&[u8]],
+    csprng: &mut T,
+) -> Result<Vec<bool>, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_arrays_each(signatures, public_keys, prehashes, contexts, true, csprng)
+}
+
+/// Verifies parallel arrays of encodings, where each of `messages` is the
+/// SHA-512 digest of the message if `prehashed` is set.
+fn verify_arrays<M, T>(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    messages: &[M],
+    contexts: &[&[u8]],
+    prehashed: bool,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    M: AsRef<[u8]> + Sync,
+    T: CryptoRng + RngCore,
+{
+    check_arrays(signatures, public_keys, messages.len(), contexts)?;
+    let sigs = signatures
+        .iter()
+        .map(|x| Signature::from_bytes(x))
+        .collect::<Result<Vec<Signature>, CryptoError>>()?;
+    if sigs.is_empty() {
+        return Err(CryptoError::InvalidSignature);
+    }
+    let pks = public_keys
+        .iter()
+        .map(|x| VerifyingKey::from_bytes(x))
+        .collect::<Result<Vec<VerifyingKey>, CryptoError>>()?;
+
+    verify_in_chunks(
+        sigs.len(),
+        constants::BATCH_CHUNK_SIZE,
+        |range| {
+            let contexts = if contexts.len() == 1 {
+                contexts
+            } else {
+                &contexts[range.clone()]
+            };
+            prepare_with_contexts(
+                &messages[range.clone()],
+                &sigs[range.clone()],
+                &pks[range],
+                contexts,
+                prehashed,
+            )
+        },
+        None,
+        BatchRandomness::Random,
+        csprng,
+    )
+}
+
+/// As `verify_arrays`, reporting the validity of each entry.
+fn verify_arrays_each<M, T>(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    messages: &[M],
+    contexts: &[&[u8]],
+    prehashed: bool,
+    csprng: &mut T,
+) -> Result<Vec<bool>, CryptoError>
+where
+    M: AsRef<[u8]>,
+    T: CryptoRng + RngCore,
+{
+    check_arrays(signatures, public_keys, messages.len(), contexts)?;
+
+    let mut positions: Vec<usize> = Vec::with_capacity(signatures.len());
+    let mut decoded_messages: Vec<&[u8]> = Vec::with_capacity(signatures.len());
+    let mut decoded_contexts: Vec<&[u8]> = Vec::with_capacity(contexts.len());
+    let mut sigs: Vec<Signature> = Vec::with_capacity(signatures.len());
+    let mut pks: Vec<VerifyingKey> = Vec::with_capacity(signatures.len());
+    for i in 0..signatures.len() {
+        if let (Ok(sig), Ok(pk)) = (
+            Signature::from_bytes(signatures[i]),
+            VerifyingKey::from_bytes(public_keys[i]),
+        ) {
+            positions.push(i);
+            decoded_messages.push(messages[i].as_ref());
+            if contexts.len() != 1 {
+                decoded_contexts.push(contexts[i]);
+            }
+            sigs.push(sig);
+            pks.push(pk);
+        }
+    }
+    let contexts = if contexts.len() == 1 {
+        contexts
+    } else {
+        &decoded_contexts
+    };
+
+    let entries = prepare_with_contexts(&decoded_messages, &sigs, &pks, contexts, prehashed)?;
+    let mut entry_results = vec![false; entries.len()];
+    find_invalid_entries(
+        &entries,
+        &mut entry_results,
+        BatchRandomness::Random,
+        csprng,
+    )?;
+
+    let mut results = vec![false; signatures.len()];
+    for (position, valid) in positions.into_iter().zip(entry_results) {
+        results[position] = valid;
+    }
+    Ok(results)
+}
+
+/// Checks that the arrays are of equal length, with either one context or one
+/// per entry, and that every context is short enough.
+fn check_arrays(
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+    message_count: usize,
+    contexts: &[&[u8]],
+) -> Result<(), CryptoError> {
+    if signatures.len() != message_count
+        || signatures.len() != public_keys.len()
+        || (contexts.len() != 1 && contexts.len() != signatures.len())
+    {
+        return Err(CryptoError::ArraysNotEqualLength);
+    }
+    contexts
+        .iter()
+        .try_for_each(|context| SignatureMode::Ed25519ph.check_context(context))
+}
+
+/// As `prepare_entries`, where `contexts` holds either one context shared by
+/// every entry or one context per entry, and each of `messages` is the
+/// SHA-512 digest of the message if `prehashed` is set.
+#[allow(non_snake_case)]
+fn prepare_with_contexts<M: AsRef<[u8]>>(
+    messages: &[M],
+    sigs: &[Signature],
+    public_keys: &[VerifyingKey],
+    contexts: &[&[u8]],
+    prehashed: bool,
+) -> Result<Vec<PreparedEntry>, CryptoError> {
+    if contexts.len() == 1 && !prehashed {
+        return prepare_entries(messages, sigs, public_keys, Some(contexts[0]));
+    }
+    Ok((0..sigs.len())
+        .map(|i| {
+            let context = if contexts.len() == 1 {
+                contexts[0]
+            } else {
+                contexts[i]
+            };
+            let (R, A) = (&sigs[i].R, public_keys[i].as_bytes());
+            let k = if prehashed {
+                prehashed_challenge(context, R, A, messages[i].as_ref())
+            } else {
+                challenge(
+                    SignatureMode::Ed25519ph,
+                    context,
+                    R,
+                    A,
+                    messages[i].as_ref(),
+                )
+            };
+            PreparedEntry {
+                R: sigs[i].R,
+                s: sigs[i].s,
+                A: public_keys[i],
+                k,
+            }
+        })
+        .collect())
+}
+
+/// One entry of a heterogeneous batch, carrying its own context and signature
+/// mode. Unlike `SignatureBatch` this does not depend on the protobuf types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchItem {
+    message: Vec<u8>,
+    context: Vec<u8>,
+    mode: SignatureMode,
+    signature: Signature,
+    public_key: VerifyingKey,
+}
+
+impl BatchItem {
+    /// Creates an entry, failing if the context length is not allowed for `mode`.
+    pub fn new(
+        message: &[u8],
+        context: &[u8],
+        mode: SignatureMode,
+        signature: Signature,
+        public_key: VerifyingKey,
+    ) -> Result<Self, CryptoError> {
+        mode.check_context(context)?;
+        Ok(BatchItem {
+            message: message.to_vec(),
+            context: context.to_vec(),
+            mode,
+            signature,
+            public_key,
+        })
+    }
+
+    /// Decodes the entries of a `SignatureBatch`, all of which are Ed25519ph
+    /// signatures under the batch's shared context.
+    pub fn from_signature_batch(batch_sigs: &SignatureBatch) -> Result<Vec<Self>, CryptoError> {
+        let messages = batch_sigs.get_messages();
+        let signatures = batch_sigs.get_signatures();
+        let public_keys = batch_sigs.get_public_keys();
+        if signatures.len() != messages.len() || signatures.len() != public_keys.len() {
+            return Err(CryptoError::ArraysNotEqualLength);
+        }
+        (0..signatures.len())
+            .map(|i| {
+                BatchItem::new(
+                    &messages[i],
+                    batch_sigs.get_context(),
+                    SignatureMode::Ed25519ph,
+                    Signature::from_bytes(&signatures[i])?,
+                    VerifyingKey::from_bytes(&public_keys[i])?,
+                )
+            })
+            .collect()
+    }
+
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    pub fn context(&self) -> &[u8] {
+        &self.context
+    }
+
+    pub fn mode(&self) -> SignatureMode {
+        self.mode
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn public_key(&self) -> &VerifyingKey {
+        &self.public_key
+    }
+
+    fn prepare(&self) -> PreparedEntry {
+        PreparedEntry {
+            R: self.signature.R,
+            s: self.signature.s,
+            A: self.public_key,
+            k: challenge(
+                self.mode,
+                &self.context,
+                &self.signature.R,
+                self.public_key.as_bytes(),
+                &self.message,
+            ),
+        }
+    }
+}
+
+/// Verifies every item at once with a single multiscalar multiplication, each
+/// under its own context and signature mode. More than
+/// `constants::BATCH_CHUNK_SIZE` items are verified in chunks of that size.
+pub fn verify_batch_items<T>(items: &[BatchItem], csprng: &mut T) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_batch_items_chunked(items, constants::BATCH_CHUNK_SIZE, csprng)
+}
+
+/// As `verify_batch_items`, verifying the items in chunks of at most
+/// `chunk_size`.
+pub fn verify_batch_items_chunked<T>(
+    items: &[BatchItem],
+    chunk_size: usize,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_items(items, chunk_size, BatchRandomness::Random, csprng)
+}
+
+/// As `verify_batch_items`, choosing the scalars which weight each item
+/// according to `randomness`.
+pub fn verify_batch_items_with_randomness<T>(
+    items: &[BatchItem],
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    verify_items(items, constants::BATCH_CHUNK_SIZE, randomness, csprng)
+}
+
+pub(crate) fn verify_items<T>(
+    items: &[BatchItem],
+    chunk_size: usize,
+    randomness: BatchRandomness,
+    csprng: &mut T,
+) -> Result<(), CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    if items.is_empty() {
+        return Err(CryptoError::InvalidSignature);
+    }
+    verify_in_chunks(
+        items.len(),
+        chunk_size,
+        |range| Ok(items[range].iter().map(BatchItem::prepare).collect()),
+        None,
+        randomness,
+        csprng,
     )
 }
 
+/// Verifies every item and reports the validity of each, in the order of
+/// `items`, failing with `CryptoError::Unknown` if `csprng` cannot supply the
+/// bytes.
+pub fn verify_batch_items_each<T>(
+    items: &[BatchItem],
+    csprng: &mut T,
+) -> Result<Vec<bool>, CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    let entries: Vec<PreparedEntry> = items.iter().map(BatchItem::prepare).collect();
+    let mut results = vec![false; entries.len()];
+    find_invalid_entries(&entries, &mut results, BatchRandomness::Random, csprng)?;
+    Ok(results)
+}
+
+/// One message to be signed by `sign_batch`, with the key and context to sign
+/// it under.
+#[derive(Clone, Copy)]
+pub struct SigningEntry<'a> {
+    pub key: &'a SigningKey,
+    pub message: &'a [u8],
+    pub context: &'a [u8],
+}
+
+/// Signs the entries with Ed25519ph, in order, as items ready for
+/// `verify_batch_items`. Each key's secret is expanded once however many
+/// entries it signs; with the `parallel` feature the entries are signed across
+/// all cores.
+pub fn sign_batch(entries: &[SigningEntry]) -> Result<Vec<BatchItem>, CryptoError> {
+    sign_each(entries.len(), |i| {
+        let entry = &entries[i];
+        let signature = entry.key.sign(entry.message, entry.context)?;
+        Ok(BatchItem {
+            message: entry.message.to_vec(),
+            context: entry.context.to_vec(),
+            mode: SignatureMode::Ed25519ph,
+            signature,
+            public_key: *entry.key.verifying_key(),
+        })
+    })
+}
+
+/// Signs the messages with Ed25519ph under a shared context, filling a
+/// `SignatureBatch` ready for `verify_batch`. Either one key signs every
+/// message, or `keys[i]` signs `messages[i]`.
+pub fn sign_signature_batch<M>(
+    keys: &[&SigningKey],
+    messages: &[M],
+    context: &[u8],
+) -> Result<SignatureBatch, CryptoError>
+where
+    M: AsRef<[u8]> + Sync,
+{
+    if keys.len() != 1 && keys.len() != messages.len() {
+        return Err(CryptoError::ArraysNotEqualLength);
+    }
+    SignatureMode::Ed25519ph.check_context(context)?;
+
+    let signatures = sign_each(messages.len(), |i| {
+        let key = keys[if keys.len() == 1 { 0 } else { i }];
+        key.sign(messages[i].as_ref(), context)
+    })?;
+
+    let mut batch_sigs = SignatureBatch::new();
+    batch_sigs.set_context(context.to_vec());
+    for (i, signature) in signatures.iter().enumerate() {
+        let key = keys[if keys.len() == 1 { 0 } else { i }];
+        batch_sigs
+            .mut_messages()
+            .push(messages[i].as_ref().to_vec());
+        batch_sigs
+            .mut_signatures()
+            .push(signature.to_bytes().to_vec());
+        batch_sigs
+            .mut_public_keys()
+            .push(key.verifying_key().to_bytes().to_vec());
+    }
+    Ok(batch_sigs)
+}
+
+/// The length of the serialized `SignatureBatch` which `sign_signature_batch`
+/// returns for `messages` and `context`, found without signing anything.
+pub fn signature_batch_length<M: AsRef<[u8]>>(messages: &[M], context: &[u8]) -> usize {
+    // Each field number fits in a one byte tag, followed by the length as a
+    // varint. An empty context is omitted, as proto3 omits default values.
+    fn field_length(length: usize) -> usize {
+        let mut varint_length = 1;
+        while length >> (7 * varint_length) != 0 {
+            varint_length += 1;
+        }
+        1 + varint_length + length
+    }
+
+    let entries: usize = messages
+        .iter()
+        .map(|message| {
+            field_length(message.as_ref().len())
+                + field_length(constants::SIGNATURE_LENGTH)
+                + field_length(constants::PUBLIC_KEY_LENGTH)
+        })
+        .sum();
+    if context.is_empty() {
+        entries
+    } else {
+        entries + field_length(context.len())
+    }
+}
+
+/// Runs `sign` for every index below `len`, across all cores with the
+/// `parallel` feature, collecting the results in order.
+fn sign_each<F, S>(len: usize, sign: F) -> Result<Vec<S>, CryptoError>
+where
+    F: Fn(usize) -> Result<S, CryptoError> + Sync,
+    S: Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        (0..len).into_par_iter().map(&sign).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        (0..len).map(sign).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ed25519_dalek::Keypair;
     use rand::rngs::OsRng;
 
     #[test]
@@ -142,28 +1187,35 @@ mod tests {
             b"'Beware the Jabberwock, my son!".to_vec(),
         ];
         let mut keypairs: Vec<Keypair> = Vec::new();
-        let mut signatures: Vec<SignatureExposed> = Vec::new();
+        let mut signatures: Vec<Signature> = Vec::new();
         let context = b"any old context";
 
-        for i in 0..messages.len() {
+        for message in messages.iter() {
             let keypair: Keypair = Keypair::generate(&mut csprng);
             let mut h = Sha512::default();
-            h.input(&messages[i]);
-            signatures.push(keypair.sign_prehashed(h, Some(context)).into());
+            h.input(message);
+            let signature = keypair.sign_prehashed(h, Some(context)).to_bytes();
+            signatures.push(Signature::from_bytes(&signature).unwrap());
             keypairs.push(keypair);
         }
 
-        let public_keys: Vec<PublicKey> = keypairs.iter().map(|key| key.public).collect();
+        let public_keys: Vec<VerifyingKey> = keypairs
+            .iter()
+            .map(|key| VerifyingKey::from_bytes(key.public.as_bytes()).unwrap())
+            .collect();
 
         let result = verify_batch_unwrapped(
             &messages,
-            &signatures.as_slice(),
+            signatures.as_slice(),
             &public_keys,
             Some(context),
+            constants::BATCH_CHUNK_SIZE,
+            None,
+            BatchRandomness::Random,
             &mut csprng,
         );
 
-        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        assert_eq!(result, Ok(()));
     }
 
     #[test]
@@ -177,30 +1229,37 @@ mod tests {
             b"'Beware the Jabberwock, my son!".to_vec(),
         ];
         let mut keypairs: Vec<Keypair> = Vec::new();
-        let mut signatures: Vec<SignatureExposed> = Vec::new();
+        let mut signatures: Vec<Signature> = Vec::new();
         let context = b"any old context";
 
-        for i in 0..messages.len() {
+        for message in messages.iter() {
             let keypair: Keypair = Keypair::generate(&mut csprng);
             let mut h = Sha512::default();
-            h.input(&messages[i]);
-            signatures.push(keypair.sign_prehashed(h, Some(context)).into());
+            h.input(message);
+            let signature = keypair.sign_prehashed(h, Some(context)).to_bytes();
+            signatures.push(Signature::from_bytes(&signature).unwrap());
             keypairs.push(keypair);
         }
         //alter a message before batch verification
         messages[4] = b"The jaws that bite, the claws that catch!".to_vec();
 
-        let public_keys: Vec<PublicKey> = keypairs.iter().map(|key| key.public).collect();
+        let public_keys: Vec<VerifyingKey> = keypairs
+            .iter()
+            .map(|key| VerifyingKey::from_bytes(key.public.as_bytes()).unwrap())
+            .collect();
 
         let result = verify_batch_unwrapped(
             &messages,
             &signatures,
             &public_keys,
             Some(context),
+            constants::BATCH_CHUNK_SIZE,
+            None,
+            BatchRandomness::Random,
             &mut csprng,
         );
 
-        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        assert_eq!(result, Err(CryptoError::BatchVerificationFailure));
     }
 
     #[test]
@@ -214,30 +1273,37 @@ mod tests {
             b"'Beware the Jabberwock, my son!".to_vec(),
         ];
         let mut keypairs: Vec<Keypair> = Vec::new();
-        let mut signatures: Vec<SignatureExposed> = Vec::new();
+        let mut signatures: Vec<Signature> = Vec::new();
         let context = b"any old context";
 
-        for i in 0..messages.len() {
+        for message in messages.iter() {
             let keypair: Keypair = Keypair::generate(&mut csprng);
             let mut h = Sha512::default();
-            h.input(&messages[i]);
-            signatures.push(keypair.sign_prehashed(h, Some(context)).into());
+            h.input(message);
+            let signature = keypair.sign_prehashed(h, Some(context)).to_bytes();
+            signatures.push(Signature::from_bytes(&signature).unwrap());
             keypairs.push(keypair);
         }
         //alter a signature before batch verification
         signatures[3] = signatures[4];
 
-        let public_keys: Vec<PublicKey> = keypairs.iter().map(|key| key.public).collect();
+        let public_keys: Vec<VerifyingKey> = keypairs
+            .iter()
+            .map(|key| VerifyingKey::from_bytes(key.public.as_bytes()).unwrap())
+            .collect();
 
         let result = verify_batch_unwrapped(
             &messages,
             &signatures,
             &public_keys,
             Some(context),
+            constants::BATCH_CHUNK_SIZE,
+            None,
+            BatchRandomness::Random,
             &mut csprng,
         );
 
-        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        assert_eq!(result, Err(CryptoError::BatchVerificationFailure));
     }
 
     #[test]
@@ -251,27 +1317,507 @@ mod tests {
             b"'Beware the Jabberwock, my son!".to_vec(),
         ];
         let mut keypairs: Vec<Keypair> = Vec::new();
-        let mut signatures: Vec<SignatureExposed> = Vec::new();
+        let mut signatures: Vec<Signature> = Vec::new();
         let context = b"any old context";
 
-        for i in 0..messages.len() {
+        for message in messages.iter() {
             let keypair: Keypair = Keypair::generate(&mut csprng);
             let mut h = Sha512::default();
-            h.input(&messages[i]);
-            signatures.push(keypair.sign_prehashed(h, Some(context)).into());
+            h.input(message);
+            let signature = keypair.sign_prehashed(h, Some(context)).to_bytes();
+            signatures.push(Signature::from_bytes(&signature).unwrap());
             keypairs.push(keypair);
         }
 
-        let public_keys: Vec<PublicKey> = keypairs.iter().map(|key| key.public).collect();
+        let public_keys: Vec<VerifyingKey> = keypairs
+            .iter()
+            .map(|key| VerifyingKey::from_bytes(key.public.as_bytes()).unwrap())
+            .collect();
 
         let result = verify_batch_unwrapped(
             &messages,
             signatures.as_slice(),
             &public_keys,
             Some(b"a different context"),
+            constants::BATCH_CHUNK_SIZE,
+            None,
+            BatchRandomness::Random,
             &mut csprng,
         );
 
-        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        assert_eq!(result, Err(CryptoError::BatchVerificationFailure));
+    }
+
+    #[test]
+    fn bisection_identifies_every_incorrect_signature() {
+        let mut csprng = OsRng {};
+        let context = b"any old context";
+        let (mut messages, signatures, public_keys) = signed_numbered_messages(13, context);
+        let invalid = [0usize, 5, 6, 12];
+        for &i in invalid.iter() {
+            messages[i].push(0);
+        }
+
+        let entries = prepare_entries(&messages, &signatures, &public_keys, Some(context)).unwrap();
+        let mut results = vec![false; entries.len()];
+        find_invalid_entries(&entries, &mut results, BatchRandomness::Random, &mut csprng);
+
+        for (i, valid) in results.iter().enumerate() {
+            assert_eq!(*valid, !invalid.contains(&i), "entry {}", i);
+        }
+    }
+
+    fn mixed_items(csprng: &mut OsRng) -> Vec<BatchItem> {
+        let cases: [(&[u8], &[u8], SignatureMode); 4] = [
+            (b"pure", b"", SignatureMode::Ed25519),
+            (b"with context", b"first context", SignatureMode::Ed25519ctx),
+            (b"prehashed", b"second context", SignatureMode::Ed25519ph),
+            (b"prehashed without context", b"", SignatureMode::Ed25519ph),
+        ];
+        cases
+            .iter()
+            .map(|(message, context, mode)| {
+                let key = SigningKey::generate(csprng);
+                let signature = key.sign_with_mode(message, context, *mode).unwrap();
+                BatchItem::new(message, context, *mode, signature, *key.verifying_key()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_verify_items_validates_mixed_contexts_and_modes() {
+        let mut csprng = OsRng {};
+        let items = mixed_items(&mut csprng);
+
+        assert_eq!(verify_batch_items(&items, &mut csprng), Ok(()));
+    }
+
+    #[test]
+    fn batch_verify_items_fails_on_item_with_wrong_mode() {
+        let mut csprng = OsRng {};
+        let mut items = mixed_items(&mut csprng);
+        items[1].mode = SignatureMode::Ed25519ph;
+
+        assert_eq!(
+            verify_batch_items(&items, &mut csprng),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+        assert_eq!(
+            verify_batch_items_each(&items, &mut csprng),
+            Ok(vec![true, false, true, true])
+        );
+    }
+
+    #[test]
+    fn batch_item_rejects_context_not_allowed_for_mode() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let signature = key.sign(b"message", b"").unwrap();
+        let result = BatchItem::new(
+            b"message",
+            b"context",
+            SignatureMode::Ed25519,
+            signature,
+            *key.verifying_key(),
+        );
+
+        assert_eq!(result, Err(CryptoError::InvalidContextLength));
+    }
+
+    /// Signs `count` distinct messages of 40 bytes each.
+    fn signed_numbered_messages(
+        count: u8,
+        context: &[u8],
+    ) -> (Vec<Vec<u8>>, Vec<Signature>, Vec<VerifyingKey>) {
+        let messages: Vec<Vec<u8>> = (0..count).map(|i| vec![i; 40]).collect();
+        let (signatures, public_keys) = testing::signed_messages(&messages, context);
+        (messages, signatures, public_keys)
+    }
+
+    #[test]
+    fn chunked_batch_verify_validates_every_chunk() {
+        let context = b"any old context";
+        let (messages, signatures, public_keys) = signed_numbered_messages(10, context);
+
+        for chunk_size in [1, 3, 10, 64].iter() {
+            let result = verify_batch_unwrapped(
+                &messages,
+                &signatures,
+                &public_keys,
+                Some(context),
+                *chunk_size,
+                None,
+                BatchRandomness::Random,
+                &mut OsRng {},
+            );
+            assert_eq!(result, Ok(()), "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn chunked_batch_verify_fails_on_incorrect_signature_in_last_chunk() {
+        let context = b"any old context";
+        let (mut messages, signatures, public_keys) = signed_numbered_messages(10, context);
+        messages[9].push(0);
+
+        let result = verify_batch_unwrapped(
+            &messages,
+            &signatures,
+            &public_keys,
+            Some(context),
+            3,
+            None,
+            BatchRandomness::Random,
+            &mut OsRng {},
+        );
+
+        assert_eq!(result, Err(CryptoError::BatchVerificationFailure));
+    }
+
+    #[test]
+    fn batch_verify_combines_repeated_signers() {
+        let context = b"any old context";
+        let keys = [
+            SigningKey::generate(&mut OsRng {}),
+            SigningKey::generate(&mut OsRng {}),
+        ];
+        let mut messages: Vec<Vec<u8>> = (0..9u8).map(|i| vec![i; 40]).collect();
+        let signatures: Vec<Signature> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| keys[i % 2].sign(message, context).unwrap())
+            .collect();
+        let public_keys: Vec<VerifyingKey> = (0..messages.len())
+            .map(|i| *keys[i % 2].verifying_key())
+            .collect();
+
+        let entries = prepare_entries(&messages, &signatures, &public_keys, Some(context)).unwrap();
+        assert_eq!(
+            verify_entries(&entries, None, BatchRandomness::Random, &mut OsRng {}),
+            Ok(true)
+        );
+
+        messages[4].push(0);
+        let entries = prepare_entries(&messages, &signatures, &public_keys, Some(context)).unwrap();
+        assert_eq!(
+            verify_entries(&entries, None, BatchRandomness::Random, &mut OsRng {}),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn single_signer_verify_validates_many_messages() {
+        let context = b"any old context";
+        let key = SigningKey::generate(&mut OsRng {});
+        let mut messages: Vec<Vec<u8>> = (0..12u8).map(|i| vec![i; 40]).collect();
+        let signatures: Vec<Signature> = messages
+            .iter()
+            .map(|message| key.sign(message, context).unwrap())
+            .collect();
+
+        assert_eq!(
+            verify_single_signer(
+                key.verifying_key(),
+                &messages,
+                &signatures,
+                context,
+                &mut OsRng {}
+            ),
+            Ok(())
+        );
+
+        let other_key = SigningKey::generate(&mut OsRng {});
+        assert_eq!(
+            verify_single_signer(
+                other_key.verifying_key(),
+                &messages,
+                &signatures,
+                context,
+                &mut OsRng {}
+            ),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+
+        messages[11].push(0);
+        assert_eq!(
+            verify_single_signer(
+                key.verifying_key(),
+                &messages,
+                &signatures,
+                context,
+                &mut OsRng {}
+            ),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+        assert_eq!(
+            verify_single_signer(
+                key.verifying_key(),
+                &messages[1..],
+                &signatures,
+                context,
+                &mut OsRng {}
+            ),
+            Err(CryptoError::ArraysNotEqualLength)
+        );
+    }
+
+    #[test]
+    fn signed_batch_items_verify() {
+        let keys: Vec<SigningKey> = (0..3)
+            .map(|_| SigningKey::generate(&mut OsRng {}))
+            .collect();
+        let messages: Vec<Vec<u8>> = (0..9u8).map(|i| vec![i; 20]).collect();
+        let entries: Vec<SigningEntry> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| SigningEntry {
+                key: &keys[i % keys.len()],
+                message,
+                context: if i % 2 == 0 { b"even" } else { b"" },
+            })
+            .collect();
+
+        let items = sign_batch(&entries).unwrap();
+        assert_eq!(items.len(), entries.len());
+        for (item, entry) in items.iter().zip(entries.iter()) {
+            assert_eq!(item.message(), entry.message);
+            assert_eq!(item.context(), entry.context);
+            assert_eq!(item.public_key(), entry.key.verifying_key());
+        }
+        assert_eq!(verify_batch_items(&items, &mut OsRng {}), Ok(()));
+
+        let too_long = [0u8; 256];
+        let entry = SigningEntry {
+            context: &too_long,
+            ..entries[0]
+        };
+        assert_eq!(sign_batch(&[entry]), Err(CryptoError::InvalidContextLength));
+    }
+
+    #[test]
+    fn signature_batch_length_matches_serialized_batch() {
+        let key = SigningKey::generate(&mut OsRng {});
+        for length in [0usize, 1, 127, 128, 16_383, 16_384].iter() {
+            let messages = [vec![0u8; *length], vec![1u8; 3]];
+            for context in [&b""[..], b"context"].iter() {
+                let batch_sigs = sign_signature_batch(&[&key], &messages, context).unwrap();
+                assert_eq!(
+                    signature_batch_length(&messages, context),
+                    batch_sigs.write_to_bytes().unwrap().len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn signed_signature_batch_verifies() {
+        let context = b"any old context";
+        let key = SigningKey::generate(&mut OsRng {});
+        let other_key = SigningKey::generate(&mut OsRng {});
+        let messages: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 20]).collect();
+
+        let mut batch_sigs = sign_signature_batch(&[&key], &messages, context).unwrap();
+        assert_eq!(batch_sigs.get_context(), &context[..]);
+        assert_eq!(try_verify_batch(&mut batch_sigs, &mut OsRng {}), Ok(()));
+
+        let keys = [&key, &other_key, &key, &other_key];
+        let mut batch_sigs = sign_signature_batch(&keys, &messages, context).unwrap();
+        assert_eq!(
+            batch_sigs.get_public_keys()[1],
+            other_key.verifying_key().to_bytes().to_vec()
+        );
+        assert_eq!(try_verify_batch(&mut batch_sigs, &mut OsRng {}), Ok(()));
+
+        assert_eq!(
+            sign_signature_batch(&keys[..2], &messages, context).err(),
+            Some(CryptoError::ArraysNotEqualLength)
+        );
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn transcript_scalars_change_with_every_signature() {
+        let context = b"any old context";
+        let (messages, signatures, public_keys) = signed_numbered_messages(2, context);
+        let entries = prepare_entries(&messages, &signatures, &public_keys, Some(context)).unwrap();
+        let transcript = BatchRandomness::Transcript(b"");
+        let zs = coefficients(&entries, transcript, &mut OsRng {}).unwrap();
+        assert_eq!(
+            Ok(zs.clone()),
+            coefficients(&entries, transcript, &mut OsRng {})
+        );
+        assert_ne!(
+            Ok(zs.clone()),
+            coefficients(
+                &entries,
+                BatchRandomness::Transcript(b"extra"),
+                &mut OsRng {}
+            )
+        );
+
+        // Shift both s so that their errors cancel under the scalars of the
+        // honest batch, which a forger could compute in advance.
+        let mut forged =
+            prepare_entries(&messages, &signatures, &public_keys, Some(context)).unwrap();
+        let t = Scalar::from(7u8);
+        forged[0].s += t * zs[1];
+        forged[1].s -= t * zs[0];
+        let residual = |zs: &[Scalar]| {
+            forged
+                .iter()
+                .zip(zs)
+                .fold(EdwardsPoint::default(), |sum, (entry, z)| {
+                    let R = entry.R.decompress().unwrap();
+                    sum + z * (entry.s * ED25519_BASEPOINT_POINT - R - entry.k * entry.A.point())
+                })
+        };
+        assert!(residual(&zs).is_identity());
+        assert!(!forged[0].verify_single() && !forged[1].verify_single());
+
+        let forged_zs = coefficients(&forged, transcript, &mut OsRng {}).unwrap();
+        assert!(!residual(&forged_zs).is_identity());
+        assert_eq!(
+            verify_entries(&forged, None, transcript, &mut OsRng {}),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn transcript_randomness_verifies_items() {
+        let context = b"any old context";
+        let (messages, signatures, public_keys) = signed_numbered_messages(5, context);
+        let mut items: Vec<BatchItem> = (0..messages.len())
+            .map(|i| {
+                BatchItem::new(
+                    &messages[i],
+                    context,
+                    SignatureMode::Ed25519ph,
+                    signatures[i],
+                    public_keys[i],
+                )
+                .unwrap()
+            })
+            .collect();
+        let transcript = BatchRandomness::Transcript(b"extra");
+
+        assert_eq!(
+            verify_batch_items_with_randomness(&items, transcript, &mut OsRng {}),
+            Ok(())
+        );
+        items[3].message.push(0);
+        assert_eq!(
+            verify_batch_items_with_randomness(&items, transcript, &mut OsRng {}),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+        assert_eq!(BatchRandomness::from_i32(1, b"extra"), Some(transcript));
+        assert_eq!(BatchRandomness::from_i32(2, b""), None);
+    }
+
+    #[test]
+    fn batch_arrays_verify_with_shared_or_per_entry_contexts() {
+        let messages: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 40]).collect();
+        let contexts: Vec<Vec<u8>> = (0..4u8).map(|i| vec![b'c'; i as usize]).collect();
+        let mut signatures = Vec::new();
+        let mut shared_signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        for (message, context) in messages.iter().zip(contexts.iter()) {
+            let key = SigningKey::generate(&mut OsRng {});
+            signatures.push(key.sign(message, context).unwrap().to_bytes());
+            shared_signatures.push(key.sign(message, b"shared").unwrap().to_bytes());
+            public_keys.push(key.verifying_key().to_bytes());
+        }
+        let signatures: Vec<&[u8]> = signatures.iter().map(|x| &x[..]).collect();
+        let shared_signatures: Vec<&[u8]> = shared_signatures.iter().map(|x| &x[..]).collect();
+        let public_keys: Vec<&[u8]> = public_keys.iter().map(|x| &x[..]).collect();
+        let messages: Vec<&[u8]> = messages.iter().map(Vec::as_slice).collect();
+        let contexts: Vec<&[u8]> = contexts.iter().map(Vec::as_slice).collect();
+
+        let verify = |signatures: &[&[u8]], contexts: &[&[u8]]| {
+            try_verify_batch_arrays(signatures, &public_keys, &messages, contexts, &mut OsRng {})
+        };
+        assert_eq!(verify(&signatures, &contexts), Ok(()));
+        assert_eq!(verify(&shared_signatures, &[b"shared"]), Ok(()));
+        assert_eq!(
+            verify(&shared_signatures, &[b"other"]),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+        assert_eq!(
+            verify(&signatures, &contexts[1..]),
+            Err(CryptoError::ArraysNotEqualLength)
+        );
+
+        let mut swapped = contexts.clone();
+        swapped.swap(1, 2);
+        assert_eq!(
+            verify_batch_arrays_each(
+                &signatures,
+                &public_keys,
+                &messages,
+                &swapped,
+                &mut OsRng {}
+            ),
+            Ok(vec![true, false, false, true])
+        );
+    }
+
+    #[test]
+    fn batch_verifies_from_prehashes() {
+        let context = b"transactions";
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        let mut prehashes = Vec::new();
+        for i in 0..4u8 {
+            let message = vec![i; 1000];
+            let key = SigningKey::generate(&mut OsRng {});
+            signatures.push(key.sign(&message, context).unwrap().to_bytes());
+            public_keys.push(key.verifying_key().to_bytes());
+            let mut prehash = [0u8; constants::PREHASH_LENGTH];
+            prehash.copy_from_slice(&Sha512::digest(&message));
+            prehashes.push(prehash);
+        }
+        let signatures: Vec<&[u8]> = signatures.iter().map(|x| &x[..]).collect();
+        let public_keys: Vec<&[u8]> = public_keys.iter().map(|x| &x[..]).collect();
+
+        assert_eq!(
+            try_verify_batch_prehashed(
+                &signatures,
+                &public_keys,
+                &prehashes,
+                &[context],
+                &mut OsRng {}
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            try_verify_batch_prehashed(
+                &signatures,
+                &public_keys,
+                &prehashes,
+                &[&context[..]; 4],
+                &mut OsRng {}
+            ),
+            Ok(())
+        );
+
+        prehashes[2][0] ^= 1;
+        assert_eq!(
+            try_verify_batch_prehashed(
+                &signatures,
+                &public_keys,
+                &prehashes,
+                &[context],
+                &mut OsRng {}
+            ),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+        assert_eq!(
+            verify_batch_prehashed_each(
+                &signatures,
+                &public_keys,
+                &prehashes,
+                &[context],
+                &mut OsRng {}
+            ),
+            Ok(vec![true, true, false, true])
+        );
     }
 }
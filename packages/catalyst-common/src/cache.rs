@@ -0,0 +1,188 @@
+//! A bounded cache of decompressed verification keys, with optional
+//! precomputed multiscalar tables for a fixed set of validator keys.
+
+use super::*;
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::VartimeEdwardsPrecomputation;
+use curve25519_dalek::traits::VartimePrecomputedMultiscalarMul;
+use std::collections::{HashMap, VecDeque};
+
+type PublicKeyBytes = [u8; constants::PUBLIC_KEY_LENGTH];
+
+/// Precomputed tables for the basepoint followed by every validator key, so
+/// that batches signed by validators need no tables built per verification.
+pub(crate) struct ValidatorTable {
+    indices: HashMap<PublicKeyBytes, usize>,
+    keys: Vec<VerifyingKey>,
+    pub(crate) precomputation: VartimeEdwardsPrecomputation,
+}
+
+impl ValidatorTable {
+    /// The position of the validator's key among the static points, after the
+    /// basepoint at position 0.
+    pub(crate) fn static_index(&self, public_key: &VerifyingKey) -> Option<usize> {
+        self.indices.get(public_key.as_bytes()).map(|i| i + 1)
+    }
+
+    /// The number of static points, including the basepoint.
+    pub(crate) fn static_len(&self) -> usize {
+        self.keys.len() + 1
+    }
+}
+
+/// Holds decompressed verification keys by their encoding, so that keys seen
+/// before are not decompressed again. Once `capacity` keys are held the
+/// oldest is evicted. Validator keys set with `set_validators` are held in
+/// addition to those and are never evicted.
+pub struct VerificationKeyCache {
+    capacity: usize,
+    keys: HashMap<PublicKeyBytes, VerifyingKey>,
+    insertion_order: VecDeque<PublicKeyBytes>,
+    validators: Option<ValidatorTable>,
+}
+
+impl VerificationKeyCache {
+    pub fn new(capacity: usize) -> Self {
+        VerificationKeyCache {
+            capacity,
+            keys: HashMap::with_capacity(capacity),
+            insertion_order: VecDeque::with_capacity(capacity),
+            validators: None,
+        }
+    }
+
+    /// Replaces the validator set, decompressing every key and precomputing
+    /// its multiscalar tables. Fails without changing the cache if any key is
+    /// not a valid public key.
+    pub fn set_validators<I>(&mut self, public_keys: I) -> Result<(), CryptoError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let keys = public_keys
+            .into_iter()
+            .map(|key| VerifyingKey::from_bytes(key.as_ref()))
+            .collect::<Result<Vec<VerifyingKey>, CryptoError>>()?;
+        let indices = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.to_bytes(), i))
+            .collect();
+        let precomputation = VartimeEdwardsPrecomputation::new(
+            std::iter::once(ED25519_BASEPOINT_POINT).chain(keys.iter().map(|key| *key.point())),
+        );
+        self.validators = Some(ValidatorTable {
+            indices,
+            keys,
+            precomputation,
+        });
+        Ok(())
+    }
+
+    /// Returns the decompressed key for `public_key`, decompressing and
+    /// caching it if it is not held already.
+    pub fn get(&mut self, public_key: &[u8]) -> Result<VerifyingKey, CryptoError> {
+        if public_key.len() != constants::PUBLIC_KEY_LENGTH {
+            return Err(CryptoError::InvalidPublicKey);
+        }
+        let mut bytes = [0u8; constants::PUBLIC_KEY_LENGTH];
+        bytes.copy_from_slice(public_key);
+
+        if let Some(table) = &self.validators {
+            if let Some(&i) = table.indices.get(&bytes) {
+                return Ok(table.keys[i]);
+            }
+        }
+        if let Some(key) = self.keys.get(&bytes) {
+            return Ok(*key);
+        }
+
+        let key = VerifyingKey::from_bytes(&bytes)?;
+        if self.capacity > 0 {
+            if self.keys.len() == self.capacity {
+                if let Some(oldest) = self.insertion_order.pop_front() {
+                    self.keys.remove(&oldest);
+                }
+            }
+            self.keys.insert(bytes, key);
+            self.insertion_order.push_back(bytes);
+        }
+        Ok(key)
+    }
+
+    /// The number of keys held, not counting validators.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn validators(&self) -> Option<&ValidatorTable> {
+        self.validators.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn public_key() -> PublicKeyBytes {
+        SigningKey::generate(&mut OsRng {})
+            .verifying_key()
+            .to_bytes()
+    }
+
+    #[test]
+    fn cache_evicts_oldest_key_when_full() {
+        let mut cache = VerificationKeyCache::new(2);
+        let keys = [public_key(), public_key(), public_key()];
+        for key in keys.iter() {
+            assert_eq!(cache.get(key).unwrap().to_bytes(), *key);
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.keys.contains_key(&keys[0]));
+        assert!(cache.keys.contains_key(&keys[1]));
+        assert!(cache.keys.contains_key(&keys[2]));
+    }
+
+    #[test]
+    fn validators_are_not_counted_or_evicted() {
+        let mut cache = VerificationKeyCache::new(1);
+        let validator = public_key();
+        cache.set_validators([validator]).unwrap();
+
+        cache.get(&public_key()).unwrap();
+        cache.get(&public_key()).unwrap();
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&validator).unwrap().to_bytes(), validator);
+        let table = cache.validators().unwrap();
+        assert_eq!(table.static_len(), 2);
+        assert_eq!(
+            table.static_index(&VerifyingKey::from_bytes(&validator).unwrap()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn cache_rejects_invalid_key_without_caching_it() {
+        let mut cache = VerificationKeyCache::new(4);
+        let mut key = public_key();
+        key[constants::PUBLIC_KEY_LENGTH - 1] ^= 0x7f;
+        while VerifyingKey::from_bytes(&key).is_ok() {
+            key[0] = key[0].wrapping_add(1);
+        }
+
+        assert_eq!(cache.get(&key), Err(CryptoError::InvalidPublicKey));
+        assert_eq!(cache.get(&key[1..]), Err(CryptoError::InvalidPublicKey));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,393 @@
+//! A compact, canonical and versioned binary encoding of signature batches
+//! and single signed items, for shipping between nodes and archiving.
+//!
+//! Unlike the protobuf encoding, every batch and item has exactly one
+//! encoding: fields appear in a fixed order, signatures and public keys have
+//! no length prefix, and no trailing bytes are allowed.
+//!
+//! # Format, version 1
+//!
+//! All integers are unsigned and little-endian. Every encoding starts with a
+//! five byte header:
+//!
+//! | Size | Field                                         |
+//! |------|-----------------------------------------------|
+//! | 3    | magic, the ASCII bytes `CSB`                  |
+//! | 1    | version, 1                                    |
+//! | 1    | kind, 1 for a batch or 2 for a signed item    |
+//!
+//! A batch of Ed25519ph signatures under a shared context, as carried by
+//! `SignatureBatch`, follows with:
+//!
+//! | Size | Field                                         |
+//! |------|-----------------------------------------------|
+//! | 1    | context length `c`, at most 255               |
+//! | `c`  | context                                       |
+//! | 4    | entry count `n`                               |
+//!
+//! and then `n` entries of:
+//!
+//! | Size | Field                                         |
+//! |------|-----------------------------------------------|
+//! | 64   | signature                                     |
+//! | 32   | public key                                    |
+//! | 4    | message length `m`                            |
+//! | `m`  | message                                       |
+//!
+//! A signed item, as held by `BatchItem`, follows with:
+//!
+//! | Size | Field                                         |
+//! |------|-----------------------------------------------|
+//! | 1    | mode, 0 for Ed25519, 1 for Ed25519ctx and 2 for Ed25519ph |
+//! | 1    | context length `c`, allowed by the mode       |
+//! | `c`  | context                                       |
+//! | 64   | signature                                     |
+//! | 32   | public key                                    |
+//! | 4    | message length `m`                            |
+//! | `m`  | message                                       |
+//!
+//! A reader must reject an encoding with any other magic, version or kind,
+//! with fewer bytes than its lengths call for, or with bytes left over. The
+//! signatures and public keys of a batch are not decoded when it is read, as
+//! with the protobuf encoding; those of a signed item are.
+
+use super::*;
+use crate::wire::SignatureBatchView;
+use std::convert::TryFrom;
+
+const MAGIC: &[u8; 3] = b"CSB";
+/// The version of the format written by this module.
+pub const VERSION: u8 = 1;
+const KIND_BATCH: u8 = 1;
+const KIND_ITEM: u8 = 2;
+
+/// Encodes a batch, failing if its arrays differ in length, if any signature
+/// or public key has the wrong length, or if its context is too long.
+/// A `SignatureBatch` is encoded through `SignatureBatchView::from_batch`.
+pub fn encode_batch(view: &SignatureBatchView) -> Result<Vec<u8>, CryptoError> {
+    let signatures = view.signatures();
+    let public_keys = view.public_keys();
+    let messages = view.messages();
+    if signatures.len() != messages.len() || signatures.len() != public_keys.len() {
+        return Err(CryptoError::ArraysNotEqualLength);
+    }
+    let count = u32::try_from(signatures.len()).map_err(|_| CryptoError::ArraysNotEqualLength)?;
+
+    let mut writer = Writer::new(KIND_BATCH);
+    writer.context(view.context())?;
+    writer.bytes(&count.to_le_bytes());
+    for i in 0..signatures.len() {
+        writer.fixed(
+            signatures[i],
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        writer.fixed(
+            public_keys[i],
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        writer.message(messages[i])?;
+    }
+    Ok(writer.bytes)
+}
+
+/// Reads a batch, borrowing every entry from `bytes`. Any deviation from the
+/// format is reported as `CryptoError::InvalidBatchMessage`.
+pub fn decode_batch(bytes: &[u8]) -> Result<SignatureBatchView<'_>, CryptoError> {
+    let mut reader = Reader::new(bytes, KIND_BATCH)?;
+    let context = reader.context()?;
+    let count = reader.u32()? as usize;
+    // Every entry takes at least this many bytes, which bounds the
+    // allocations below by the length of the input.
+    let min_entry = constants::SIGNATURE_LENGTH + constants::PUBLIC_KEY_LENGTH + 4;
+    if count > reader.bytes.len() / min_entry {
+        return Err(CryptoError::InvalidBatchMessage);
+    }
+
+    let mut signatures = Vec::with_capacity(count);
+    let mut public_keys = Vec::with_capacity(count);
+    let mut messages = Vec::with_capacity(count);
+    for _ in 0..count {
+        signatures.push(reader.take(constants::SIGNATURE_LENGTH)?);
+        public_keys.push(reader.take(constants::PUBLIC_KEY_LENGTH)?);
+        messages.push(reader.message()?);
+    }
+    reader.finish()?;
+    Ok(SignatureBatchView::from_parts(
+        signatures,
+        public_keys,
+        messages,
+        context,
+    ))
+}
+
+/// Encodes a `SignatureBatch`, as `encode_batch`.
+pub fn encode_signature_batch(batch_sigs: &SignatureBatch) -> Result<Vec<u8>, CryptoError> {
+    encode_batch(&SignatureBatchView::from_batch(batch_sigs))
+}
+
+/// Reads a batch into a `SignatureBatch`, as `decode_batch`.
+pub fn decode_signature_batch(bytes: &[u8]) -> Result<SignatureBatch, CryptoError> {
+    decode_batch(bytes).map(|view| view.to_batch())
+}
+
+/// Encodes a signed item, failing only if its message is longer than the
+/// format allows.
+pub fn encode_item(item: &BatchItem) -> Result<Vec<u8>, CryptoError> {
+    let mut writer = Writer::new(KIND_ITEM);
+    writer.bytes(&[item.mode() as u8]);
+    writer.context(item.context())?;
+    writer.bytes(&item.signature().to_bytes());
+    writer.bytes(item.public_key().as_bytes());
+    writer.message(item.message())?;
+    Ok(writer.bytes)
+}
+
+/// Reads a signed item. A malformed encoding is reported as
+/// `CryptoError::InvalidBatchMessage`, while a signature, public key or
+/// context which is well framed but invalid is reported as it would be by
+/// `BatchItem::new`.
+pub fn decode_item(bytes: &[u8]) -> Result<BatchItem, CryptoError> {
+    let mut reader = Reader::new(bytes, KIND_ITEM)?;
+    let mode = SignatureMode::from_i32(i32::from(reader.byte()?))
+        .ok_or(CryptoError::InvalidBatchMessage)?;
+    let context = reader.context()?;
+    let signature = reader.take(constants::SIGNATURE_LENGTH)?;
+    let public_key = reader.take(constants::PUBLIC_KEY_LENGTH)?;
+    let message = reader.message()?;
+    reader.finish()?;
+    BatchItem::new(
+        message,
+        context,
+        mode,
+        Signature::from_bytes(signature)?,
+        VerifyingKey::from_bytes(public_key)?,
+    )
+}
+
+/// Appends the fields of one encoding after its header.
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn new(kind: u8) -> Self {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(kind);
+        Writer { bytes }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn fixed(
+        &mut self,
+        bytes: &[u8],
+        length: usize,
+        error: CryptoError,
+    ) -> Result<(), CryptoError> {
+        if bytes.len() != length {
+            return Err(error);
+        }
+        self.bytes(bytes);
+        Ok(())
+    }
+
+    fn context(&mut self, context: &[u8]) -> Result<(), CryptoError> {
+        let length = u8::try_from(context.len()).map_err(|_| CryptoError::InvalidContextLength)?;
+        self.bytes(&[length]);
+        self.bytes(context);
+        Ok(())
+    }
+
+    fn message(&mut self, message: &[u8]) -> Result<(), CryptoError> {
+        let length = u32::try_from(message.len()).map_err(|_| CryptoError::InvalidBatchMessage)?;
+        self.bytes(&length.to_le_bytes());
+        self.bytes(message);
+        Ok(())
+    }
+}
+
+/// Reads the fields of one encoding from the front of `bytes`, failing with
+/// `CryptoError::InvalidBatchMessage` on any deviation from the format.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], kind: u8) -> Result<Self, CryptoError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC || reader.byte()? != VERSION || reader.byte()? != kind
+        {
+            return Err(CryptoError::InvalidBatchMessage);
+        }
+        Ok(reader)
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], CryptoError> {
+        if length > self.bytes.len() {
+            return Err(CryptoError::InvalidBatchMessage);
+        }
+        let (value, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> Result<u8, CryptoError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, CryptoError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn context(&mut self) -> Result<&'a [u8], CryptoError> {
+        let length = self.byte()?;
+        self.take(usize::from(length))
+    }
+
+    fn message(&mut self) -> Result<&'a [u8], CryptoError> {
+        let length = self.u32()?;
+        self.take(length as usize)
+    }
+
+    fn finish(self) -> Result<(), CryptoError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(CryptoError::InvalidBatchMessage)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn signed_batch() -> SignatureBatch {
+        testing::signed_batch(&[b"first", b"", b"third"], b"context")
+    }
+
+    #[test]
+    fn batch_round_trips_through_protobuf() {
+        let batch_sigs = signed_batch();
+        let bytes = encode_signature_batch(&batch_sigs).unwrap();
+
+        assert_eq!(&bytes[..5], b"CSB\x01\x01");
+        assert_eq!(bytes.len(), 5 + 1 + 7 + 4 + 3 * (64 + 32 + 4) + 10);
+        let decoded = decode_signature_batch(&bytes).unwrap();
+        assert_eq!(decoded, batch_sigs);
+        assert_eq!(
+            decode_batch(&bytes).unwrap(),
+            SignatureBatchView::from_batch(&batch_sigs)
+        );
+        assert_eq!(
+            batch::try_verify_batch(&mut decoded.clone(), &mut OsRng {}),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn batch_encoding_checks_lengths() {
+        let mut batch_sigs = signed_batch();
+        batch_sigs.mut_public_keys()[1].push(0);
+        assert_eq!(
+            encode_signature_batch(&batch_sigs),
+            Err(CryptoError::InvalidPublicKey)
+        );
+
+        let mut batch_sigs = signed_batch();
+        batch_sigs.mut_messages().pop();
+        assert_eq!(
+            encode_signature_batch(&batch_sigs),
+            Err(CryptoError::ArraysNotEqualLength)
+        );
+
+        let mut batch_sigs = signed_batch();
+        batch_sigs.set_context(vec![0u8; constants::CONTEXT_MAX_LENGTH + 1]);
+        assert_eq!(
+            encode_signature_batch(&batch_sigs),
+            Err(CryptoError::InvalidContextLength)
+        );
+    }
+
+    #[test]
+    fn batch_decoding_is_strict() {
+        let bytes = encode_signature_batch(&signed_batch()).unwrap();
+        let mut malformed: Vec<Vec<u8>> = (0..bytes.len()).map(|i| bytes[..i].to_vec()).collect();
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        malformed.push(trailing);
+        for (position, value) in [(0, b'X'), (3, 2), (4, KIND_ITEM)].iter() {
+            let mut altered = bytes.clone();
+            altered[*position] = *value;
+            malformed.push(altered);
+        }
+        // An entry count far beyond what the input could hold.
+        let mut count = bytes.clone();
+        count[13..17].copy_from_slice(&u32::MAX.to_le_bytes());
+        malformed.push(count);
+
+        for bytes in malformed.iter() {
+            assert_eq!(
+                decode_batch(bytes),
+                Err(CryptoError::InvalidBatchMessage),
+                "{:?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn item_round_trips() {
+        let key = SigningKey::generate(&mut OsRng {});
+        for (mode, context) in [
+            (SignatureMode::Ed25519, &b""[..]),
+            (SignatureMode::Ed25519ctx, b"context"),
+            (SignatureMode::Ed25519ph, b"context"),
+        ]
+        .iter()
+        {
+            let signature = key.sign_with_mode(b"message", context, *mode).unwrap();
+            let item = BatchItem::new(b"message", context, *mode, signature, *key.verifying_key())
+                .unwrap();
+            let bytes = encode_item(&item).unwrap();
+
+            assert_eq!(bytes[5], *mode as u8);
+            assert_eq!(decode_item(&bytes), Ok(item));
+            assert_eq!(
+                decode_item(&bytes[..bytes.len() - 1]),
+                Err(CryptoError::InvalidBatchMessage)
+            );
+            assert_eq!(decode_batch(&bytes), Err(CryptoError::InvalidBatchMessage));
+        }
+    }
+
+    #[test]
+    fn item_decoding_checks_mode_and_context() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let signature = key.sign_with_mode(b"message", b"context", SignatureMode::Ed25519ctx);
+        let item = BatchItem::new(
+            b"message",
+            b"context",
+            SignatureMode::Ed25519ctx,
+            signature.unwrap(),
+            *key.verifying_key(),
+        )
+        .unwrap();
+        let bytes = encode_item(&item).unwrap();
+
+        let mut pure = bytes.clone();
+        pure[5] = SignatureMode::Ed25519 as u8;
+        assert_eq!(decode_item(&pure), Err(CryptoError::InvalidContextLength));
+        let mut unknown = bytes;
+        unknown[5] = 3;
+        assert_eq!(decode_item(&unknown), Err(CryptoError::InvalidBatchMessage));
+    }
+}
@@ -9,5 +9,12 @@ pub const PRIVATE_KEY_LENGTH: usize = 32;
 /// The length of an ed25519ph `PublicKey`, in bytes.
 pub const PUBLIC_KEY_LENGTH: usize = 32;
 
+/// The length of the SHA-512 digest of a message which ed25519ph signs, in bytes.
+pub const PREHASH_LENGTH: usize = 64;
+
 /// The max of the ed25519ph context, in bytes.
 pub const CONTEXT_MAX_LENGTH: usize = 255;
+
+/// The number of signatures verified together by a batch before it is split
+/// into chunks, which bounds the memory used by a single multiscalar multiplication.
+pub const BATCH_CHUNK_SIZE: usize = 1024;
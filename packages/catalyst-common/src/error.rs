@@ -0,0 +1,134 @@
+//! Typed errors for the Rust API, convertible to and from the protobuf `ErrorCode`.
+
+use super::*;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+/// Errors which may occur while creating or verifying ed25519ph signatures.
+///
+/// Every variant corresponds to exactly one non-success `ErrorCode`, so that
+/// the ffi can continue to hand plain integer codes across the C ABI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CryptoError {
+    /// An error which does not fit any of the other variants.
+    Unknown,
+    /// The signature bytes could not be decoded.
+    InvalidSignature,
+    /// The public key bytes do not decompress to a point on the curve.
+    InvalidPublicKey,
+    /// The private key bytes could not be decoded.
+    InvalidPrivateKey,
+    /// The signature does not match the public key, message and context.
+    SignatureVerificationFailure,
+    /// The context is longer than `constants::CONTEXT_MAX_LENGTH` bytes.
+    InvalidContextLength,
+    /// The serialized batch could not be decoded.
+    InvalidBatchMessage,
+    /// The signatures, public keys and messages of a batch differ in number.
+    ArraysNotEqualLength,
+    /// At least one signature in the batch is not valid.
+    BatchVerificationFailure,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            CryptoError::Unknown => "unknown error",
+            CryptoError::InvalidSignature => "invalid signature",
+            CryptoError::InvalidPublicKey => "invalid public key",
+            CryptoError::InvalidPrivateKey => "invalid private key",
+            CryptoError::SignatureVerificationFailure => "signature verification failed",
+            CryptoError::InvalidContextLength => "context exceeds the maximum length",
+            CryptoError::InvalidBatchMessage => "invalid signature batch message",
+            CryptoError::ArraysNotEqualLength => "batch arrays are not of equal length",
+            CryptoError::BatchVerificationFailure => "batch verification failed",
+        };
+        f.write_str(description)
+    }
+}
+
+impl Error for CryptoError {}
+
+impl From<CryptoError> for ErrorCode {
+    fn from(error: CryptoError) -> Self {
+        match error {
+            CryptoError::Unknown => ErrorCode::ERROR_CODE_UNKNOWN,
+            CryptoError::InvalidSignature => ErrorCode::INVALID_SIGNATURE,
+            CryptoError::InvalidPublicKey => ErrorCode::INVALID_PUBLIC_KEY,
+            CryptoError::InvalidPrivateKey => ErrorCode::INVALID_PRIVATE_KEY,
+            CryptoError::SignatureVerificationFailure => ErrorCode::SIGNATURE_VERIFICATION_FAILURE,
+            CryptoError::InvalidContextLength => ErrorCode::INVALID_CONTEXT_LENGTH,
+            CryptoError::InvalidBatchMessage => ErrorCode::INVALID_BATCH_MESSAGE,
+            CryptoError::ArraysNotEqualLength => ErrorCode::ARRAYS_NOT_EQUAL_LENGTH,
+            CryptoError::BatchVerificationFailure => ErrorCode::BATCH_VERIFICATION_FAILURE,
+        }
+    }
+}
+
+/// Fails with `ErrorCode::NO_ERROR` as that code does not describe an error.
+impl TryFrom<ErrorCode> for CryptoError {
+    type Error = ErrorCode;
+
+    fn try_from(code: ErrorCode) -> Result<Self, Self::Error> {
+        match code {
+            ErrorCode::ERROR_CODE_UNKNOWN => Ok(CryptoError::Unknown),
+            ErrorCode::INVALID_SIGNATURE => Ok(CryptoError::InvalidSignature),
+            ErrorCode::INVALID_PUBLIC_KEY => Ok(CryptoError::InvalidPublicKey),
+            ErrorCode::INVALID_PRIVATE_KEY => Ok(CryptoError::InvalidPrivateKey),
+            ErrorCode::SIGNATURE_VERIFICATION_FAILURE => {
+                Ok(CryptoError::SignatureVerificationFailure)
+            }
+            ErrorCode::INVALID_CONTEXT_LENGTH => Ok(CryptoError::InvalidContextLength),
+            ErrorCode::INVALID_BATCH_MESSAGE => Ok(CryptoError::InvalidBatchMessage),
+            ErrorCode::ARRAYS_NOT_EQUAL_LENGTH => Ok(CryptoError::ArraysNotEqualLength),
+            ErrorCode::BATCH_VERIFICATION_FAILURE => Ok(CryptoError::BatchVerificationFailure),
+            ErrorCode::NO_ERROR => Err(ErrorCode::NO_ERROR),
+        }
+    }
+}
+
+/// Converts a result into the integer error code returned over the ffi.
+pub fn to_error_code<T>(result: Result<T, CryptoError>) -> i32 {
+    match result {
+        Ok(_) => ErrorCode::NO_ERROR.value(),
+        Err(error) => ErrorCode::from(error).value(),
+    }
+}
+
+/// Converts an integer error code back into a result. Values which are not a
+/// known `ErrorCode` are reported as `CryptoError::Unknown`.
+pub fn from_error_code(code: i32) -> Result<(), CryptoError> {
+    match ErrorCode::from_i32(code) {
+        Some(ErrorCode::NO_ERROR) => Ok(()),
+        Some(code) => Err(CryptoError::try_from(code).unwrap_or(CryptoError::Unknown)),
+        None => Err(CryptoError::Unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_error_round_trips_through_error_code() {
+        for code in ErrorCode::values() {
+            match CryptoError::try_from(*code) {
+                Ok(error) => assert_eq!(ErrorCode::from(error), *code),
+                Err(code) => assert_eq!(code, ErrorCode::NO_ERROR),
+            }
+        }
+    }
+
+    #[test]
+    fn integer_codes_round_trip_through_result() {
+        for code in ErrorCode::values() {
+            assert_eq!(to_error_code(from_error_code(code.value())), code.value());
+        }
+    }
+
+    #[test]
+    fn unrecognised_integer_code_is_unknown_error() {
+        assert_eq!(from_error_code(-1), Err(CryptoError::Unknown));
+    }
+}
@@ -18,16 +18,16 @@ impl From<Signature> for SignatureExposed {
 }
 
 pub trait PublicKeyExt {
-    fn to_decompressed_point(&self) -> EdwardsPoint;
+    fn to_decompressed_point(&self) -> Result<EdwardsPoint, CryptoError>;
 }
 
 impl PublicKeyExt for PublicKey {
-    fn to_decompressed_point(&self) -> EdwardsPoint {
+    fn to_decompressed_point(&self) -> Result<EdwardsPoint, CryptoError> {
         let bytes = self.to_bytes();
         let mut bits: [u8; 32] = [0u8; 32];
         bits.copy_from_slice(&bytes[..32]);
 
         let compressed = CompressedEdwardsY(bits);
-        compressed.decompress().unwrap()
+        compressed.decompress().ok_or(CryptoError::InvalidPublicKey)
     }
 }
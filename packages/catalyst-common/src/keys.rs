@@ -1,38 +1,457 @@
 //! ed25519ph keys
 
 use super::*;
+use crate::signature::{
+    challenge, domain_hash, equation_holds, prehashed_challenge, Signature, SignatureMode,
+};
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use ed25519_dalek::{Digest, Sha512};
 use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// A private key which has been expanded once, so that it can sign any
+/// number of messages without re-deriving the secret scalar or public key.
+pub struct SigningKey {
+    secret: SecretKey,
+    scalar: Scalar,
+    nonce: [u8; 32],
+    verifying_key: VerifyingKey,
+}
+
+impl SigningKey {
+    /// Expands a private key of `constants::PRIVATE_KEY_LENGTH` bytes.
+    pub fn from_bytes(private_key: &[u8]) -> Result<Self, CryptoError> {
+        let secret =
+            SecretKey::from_bytes(private_key).map_err(|_| CryptoError::InvalidPrivateKey)?;
+        Ok(Self::from_secret(secret))
+    }
+
+    /// Generates a new random signing key, panicking if `csprng` fails as
+    /// `RngCore::fill_bytes` does.
+    pub fn generate<T>(mut csprng: &mut T) -> Self
+    where
+        T: CryptoRng + RngCore,
+    {
+        Self::from_secret(SecretKey::generate(&mut csprng))
+    }
+
+    /// Generates a new random signing key, failing with `CryptoError::Unknown`
+    /// if `csprng` cannot supply the bytes.
+    pub fn try_generate<T>(csprng: &mut T) -> Result<Self, CryptoError>
+    where
+        T: CryptoRng + RngCore,
+    {
+        let mut private_key = try_generate_private_key(csprng)?;
+        let key = Self::from_bytes(&private_key);
+        private_key.zeroize();
+        key
+    }
+
+    fn from_secret(secret: SecretKey) -> Self {
+        let mut hash = Sha512::digest(secret.as_bytes());
+        let mut lower = [0u8; 32];
+        let mut nonce = [0u8; 32];
+        lower.copy_from_slice(&hash[..32]);
+        nonce.copy_from_slice(&hash[32..]);
+        hash.as_mut_slice().zeroize();
+
+        lower[0] &= 248;
+        lower[31] &= 63;
+        lower[31] |= 64;
+        let scalar = Scalar::from_bits(lower);
+        lower.zeroize();
+
+        let point = &scalar * &ED25519_BASEPOINT_TABLE;
+        let verifying_key = VerifyingKey {
+            compressed: point.compress(),
+            point,
+        };
+
+        SigningKey {
+            secret,
+            scalar,
+            nonce,
+            verifying_key,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; constants::PRIVATE_KEY_LENGTH] {
+        self.secret.to_bytes()
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.verifying_key
+    }
+
+    /// Signs the message under the given context using Ed25519ph.
+    pub fn sign(&self, message: &[u8], context: &[u8]) -> Result<Signature, CryptoError> {
+        self.sign_with_mode(message, context, SignatureMode::Ed25519ph)
+    }
+
+    /// Signs as `sign_with_mode`, first checking this key's verifying key
+    /// against `policy` so that no signature is made which the policy would
+    /// reject, such as one by a weak key.
+    pub fn sign_with_policy(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        mode: SignatureMode,
+        policy: &VerificationPolicy,
+    ) -> Result<Signature, CryptoError> {
+        policy.check_public_key(&self.verifying_key)?;
+        self.sign_with_mode(message, context, mode)
+    }
+
+    /// Signs the message under the given context using the RFC 8032 variant `mode`.
+    pub fn sign_with_mode(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        mode: SignatureMode,
+    ) -> Result<Signature, CryptoError> {
+        mode.check_context(context)?;
+        let domain = domain_hash(mode, context);
+        Ok(match mode {
+            SignatureMode::Ed25519ph => self.sign_in_domain(domain, &Sha512::digest(message), None),
+            _ => self.sign_in_domain(domain, message, None),
+        })
+    }
+
+    /// Signs as `sign_with_mode`, applying the countermeasures of `options`.
+    /// A hedged nonce draws its randomness from `csprng`, failing with
+    /// `CryptoError::Unknown` if `csprng` reports an error, and a signature
+    /// which fails the self check is reported as
+    /// `CryptoError::SignatureVerificationFailure` rather than returned.
+    pub fn sign_with_options<T>(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        mode: SignatureMode,
+        options: &SigningOptions,
+        csprng: &mut T,
+    ) -> Result<Signature, CryptoError>
+    where
+        T: CryptoRng + RngCore,
+    {
+        mode.check_context(context)?;
+        let mut noise = [0u8; 32];
+        let noise_used = if options.hedged_nonce {
+            csprng
+                .try_fill_bytes(&mut noise)
+                .map_err(|_| CryptoError::Unknown)?;
+            Some(&noise)
+        } else {
+            None
+        };
+        let domain = domain_hash(mode, context);
+        let signature = match mode {
+            SignatureMode::Ed25519ph => {
+                self.sign_in_domain(domain, &Sha512::digest(message), noise_used)
+            }
+            _ => self.sign_in_domain(domain, message, noise_used),
+        };
+        noise.zeroize();
+
+        // The message is hashed again rather than reusing any value from
+        // signing, so that a fault in either computation is caught.
+        if options.verify_after_sign {
+            self.verifying_key
+                .verify_with_mode(message, context, &signature, mode)?;
+        }
+        Ok(signature)
+    }
+
+    /// Signs the SHA-512 digest of a message under the given context using
+    /// Ed25519ph, making the signature `sign` would make over the message.
+    pub fn sign_prehashed(
+        &self,
+        prehash: &[u8; constants::PREHASH_LENGTH],
+        context: &[u8],
+    ) -> Result<Signature, CryptoError> {
+        SignatureMode::Ed25519ph.check_context(context)?;
+        Ok(self.sign_in_domain(
+            domain_hash(SignatureMode::Ed25519ph, context),
+            prehash,
+            None,
+        ))
+    }
+
+    /// Signs `message`, which is the prehash for Ed25519ph, after the domain
+    /// separator already hashed into `domain`. Any `noise` is hashed into the
+    /// nonce ahead of the secret nonce key.
+    #[allow(non_snake_case)]
+    fn sign_in_domain(
+        &self,
+        domain: Sha512,
+        message: &[u8],
+        noise: Option<&[u8; 32]>,
+    ) -> Signature {
+        let mut nonce_hash = domain.clone();
+        if let Some(noise) = noise {
+            nonce_hash.input(noise);
+        }
+        let mut r = Scalar::from_hash(nonce_hash.chain(self.nonce).chain(message));
+        let R = (&r * &ED25519_BASEPOINT_TABLE).compress();
+
+        let k = Scalar::from_hash(
+            domain
+                .chain(R.as_bytes())
+                .chain(self.verifying_key.as_bytes())
+                .chain(message),
+        );
+        let s = k * self.scalar + r;
+        r.zeroize();
+
+        Signature { R, s }
+    }
+}
+
+/// Wipes the expanded secret, while `SecretKey` wipes itself.
+impl Drop for SigningKey {
+    fn drop(&mut self) {
+        self.scalar.zeroize();
+        self.nonce.zeroize();
+    }
+}
+
+/// A public key which has been decompressed once, so that it can verify any
+/// number of signatures without repeating the decompression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VerifyingKey {
+    compressed: CompressedEdwardsY,
+    point: EdwardsPoint,
+}
+
+impl VerifyingKey {
+    /// Decompresses a public key, failing if it is not
+    /// `constants::PUBLIC_KEY_LENGTH` bytes long or not a point on the curve.
+    pub fn from_bytes(public_key: &[u8]) -> Result<Self, CryptoError> {
+        if public_key.len() != constants::PUBLIC_KEY_LENGTH {
+            return Err(CryptoError::InvalidPublicKey);
+        }
+        let compressed = CompressedEdwardsY::from_slice(public_key);
+        let point = compressed
+            .decompress()
+            .ok_or(CryptoError::InvalidPublicKey)?;
+        Ok(VerifyingKey { compressed, point })
+    }
+
+    pub fn as_bytes(&self) -> &[u8; constants::PUBLIC_KEY_LENGTH] {
+        self.compressed.as_bytes()
+    }
+
+    pub fn to_bytes(&self) -> [u8; constants::PUBLIC_KEY_LENGTH] {
+        self.compressed.to_bytes()
+    }
+
+    pub(crate) fn point(&self) -> &EdwardsPoint {
+        &self.point
+    }
+
+    /// Describes the point this key encodes.
+    pub fn report(&self) -> PublicKeyReport {
+        PublicKeyReport {
+            is_canonical: self.point.compress() == self.compressed,
+            is_small_order: self.point.is_small_order(),
+            is_torsion_free: self.point.is_torsion_free(),
+            is_identity: self.point.is_identity(),
+        }
+    }
+
+    /// Verifies that the Ed25519ph signature was made over the message and
+    /// context by the holder of the corresponding signing key.
+    pub fn verify(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        signature: &Signature,
+    ) -> Result<(), CryptoError> {
+        self.verify_with_mode(message, context, signature, SignatureMode::Ed25519ph)
+    }
+
+    /// Verifies a signature made using the RFC 8032 variant `mode`, applying
+    /// the checks of `policy` to this key and the signature first.
+    pub fn verify_with_policy(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        signature: &Signature,
+        mode: SignatureMode,
+        policy: &VerificationPolicy,
+    ) -> Result<(), CryptoError> {
+        policy.check_public_key(self)?;
+        policy.check_signature(signature)?;
+        self.verify_with_mode(message, context, signature, mode)
+    }
+
+    /// Verifies a signature made using the RFC 8032 variant `mode`.
+    pub fn verify_with_mode(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        signature: &Signature,
+        mode: SignatureMode,
+    ) -> Result<(), CryptoError> {
+        mode.check_context(context)?;
+        let k = challenge(mode, context, &signature.R, self.as_bytes(), message);
+        self.check_equation(signature, &k)
+    }
+
+    /// Verifies an Ed25519ph signature given the SHA-512 digest of the message
+    /// rather than the message itself.
+    pub fn verify_prehashed(
+        &self,
+        prehash: &[u8; constants::PREHASH_LENGTH],
+        context: &[u8],
+        signature: &Signature,
+    ) -> Result<(), CryptoError> {
+        SignatureMode::Ed25519ph.check_context(context)?;
+        let k = prehashed_challenge(context, &signature.R, self.as_bytes(), prehash);
+        self.check_equation(signature, &k)
+    }
+
+    /// As `verify_prehashed`, applying the checks of `policy` to this key and
+    /// the signature first.
+    pub fn verify_prehashed_with_policy(
+        &self,
+        prehash: &[u8; constants::PREHASH_LENGTH],
+        context: &[u8],
+        signature: &Signature,
+        policy: &VerificationPolicy,
+    ) -> Result<(), CryptoError> {
+        policy.check_public_key(self)?;
+        policy.check_signature(signature)?;
+        self.verify_prehashed(prehash, context, signature)
+    }
+
+    fn check_equation(&self, signature: &Signature, k: &Scalar) -> Result<(), CryptoError> {
+        if equation_holds(&signature.R, &signature.s, &self.point, k) {
+            Ok(())
+        } else {
+            Err(CryptoError::SignatureVerificationFailure)
+        }
+    }
+}
+
+/// Set in `PublicKeyReport::to_flags` if the key is the canonical encoding of its point.
+pub const PUBLIC_KEY_CANONICAL: u32 = 1;
+/// Set in `PublicKeyReport::to_flags` if the point has small order.
+pub const PUBLIC_KEY_SMALL_ORDER: u32 = 1 << 1;
+/// Set in `PublicKeyReport::to_flags` if the point lies in the prime-order subgroup.
+pub const PUBLIC_KEY_TORSION_FREE: u32 = 1 << 2;
+/// Set in `PublicKeyReport::to_flags` if the point is the identity.
+pub const PUBLIC_KEY_IDENTITY: u32 = 1 << 3;
+
+/// The properties of the point encoded by a public key. Keys derived from a
+/// private key are always canonical and torsion free, and never of small order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PublicKeyReport {
+    pub is_canonical: bool,
+    pub is_small_order: bool,
+    pub is_torsion_free: bool,
+    pub is_identity: bool,
+}
+
+impl PublicKeyReport {
+    /// Whether the key is weak, that is of small order, in which case a
+    /// signature valid for every message can be made without a private key.
+    /// The identity is of small order. Weak keys are rejected by a
+    /// `VerificationPolicy` with `reject_small_order_keys` set.
+    pub fn is_weak(&self) -> bool {
+        self.is_small_order
+    }
+
+    /// Encodes the report as a bitmask of the `PUBLIC_KEY_*` flags.
+    pub fn to_flags(self) -> u32 {
+        let mut flags = 0;
+        if self.is_canonical {
+            flags |= PUBLIC_KEY_CANONICAL;
+        }
+        if self.is_small_order {
+            flags |= PUBLIC_KEY_SMALL_ORDER;
+        }
+        if self.is_torsion_free {
+            flags |= PUBLIC_KEY_TORSION_FREE;
+        }
+        if self.is_identity {
+            flags |= PUBLIC_KEY_IDENTITY;
+        }
+        flags
+    }
+}
 
 pub fn publickey_from_private(
     out_publickey: &mut [u8; constants::PUBLIC_KEY_LENGTH],
     private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
 ) -> i32 {
-    let secret_key: SecretKey = match SecretKey::from_bytes(private_key) {
-        Ok(secret_key) => secret_key,
-        Err(_) => return ErrorCode::INVALID_PRIVATE_KEY.value(),
-    };
-    let public_key: PublicKey = (&secret_key).into();
-    out_publickey.copy_from_slice(&public_key.to_bytes());
-    ErrorCode::NO_ERROR.value()
+    error::to_error_code(
+        try_publickey_from_private(private_key)
+            .map(|public_key| out_publickey.copy_from_slice(&public_key)),
+    )
 }
 
-pub fn validate_public_key(public_key: &[u8;constants::PUBLIC_KEY_LENGTH]) -> i32 {
-    match PublicKey::from_bytes(public_key) {
-        Ok(_public_key) => ErrorCode::NO_ERROR.value(),
-        Err(_) => ErrorCode::INVALID_PUBLIC_KEY.value(),
-    }
+/// Calculates the public key corresponding to a private key.
+pub fn try_publickey_from_private(
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+) -> Result<[u8; constants::PUBLIC_KEY_LENGTH], CryptoError> {
+    SigningKey::from_bytes(private_key).map(|key| key.verifying_key().to_bytes())
+}
+
+pub fn validate_public_key(public_key: &[u8; constants::PUBLIC_KEY_LENGTH]) -> i32 {
+    error::to_error_code(try_validate_public_key(public_key))
+}
+
+/// Checks that the public key decompresses to a point on the curve.
+pub fn try_validate_public_key(
+    public_key: &[u8; constants::PUBLIC_KEY_LENGTH],
+) -> Result<(), CryptoError> {
+    VerifyingKey::from_bytes(public_key).map(|_| ())
+}
+
+/// Checks that the public key decompresses to a point on the curve and passes
+/// the public key checks of `policy`.
+pub fn try_validate_public_key_with_policy(
+    public_key: &[u8; constants::PUBLIC_KEY_LENGTH],
+    policy: &VerificationPolicy,
+) -> Result<(), CryptoError> {
+    policy.check_public_key(&VerifyingKey::from_bytes(public_key)?)
+}
+
+/// Reports on the point encoded by the public key, failing if it does not
+/// decompress to a point on the curve.
+pub fn inspect_public_key(public_key: &[u8]) -> Result<PublicKeyReport, CryptoError> {
+    VerifyingKey::from_bytes(public_key).map(|key| key.report())
 }
 
 pub fn generate_private_key<T>(
     out_key: &mut [u8; constants::PRIVATE_KEY_LENGTH],
-    mut csprng: &mut T,
+    csprng: &mut T,
 ) -> i32
 where
     T: CryptoRng + RngCore,
 {
-    let secret_key: SecretKey = SecretKey::generate(&mut csprng);
-    out_key.copy_from_slice(&secret_key.to_bytes());
-    ErrorCode::NO_ERROR.value()
+    error::to_error_code(try_generate_private_key(csprng).map(|mut private_key| {
+        out_key.copy_from_slice(&private_key);
+        private_key.zeroize();
+    }))
+}
+
+/// Generates a new random private key, failing with `CryptoError::Unknown` if
+/// `csprng` cannot supply the bytes.
+pub fn try_generate_private_key<T>(
+    csprng: &mut T,
+) -> Result<[u8; constants::PRIVATE_KEY_LENGTH], CryptoError>
+where
+    T: CryptoRng + RngCore,
+{
+    let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+    csprng
+        .try_fill_bytes(&mut private_key)
+        .map_err(|_| CryptoError::Unknown)?;
+    Ok(private_key)
 }
 
 #[cfg(test)]
@@ -70,4 +489,220 @@ mod tests {
         let public_key: PublicKey = (&private_key).into();
         assert_eq!(out_publickey, public_key.to_bytes());
     }
+
+    #[test]
+    fn try_validate_public_key_rejects_point_not_on_curve() {
+        let mut public_key = try_publickey_from_private(&[0u8; constants::PRIVATE_KEY_LENGTH])
+            .expect("failed to derive public key");
+        assert_eq!(try_validate_public_key(&public_key), Ok(()));
+
+        public_key[constants::PUBLIC_KEY_LENGTH - 1] =
+            public_key[constants::PUBLIC_KEY_LENGTH - 1].wrapping_add(1u8);
+        assert_eq!(
+            try_validate_public_key(&public_key),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn signing_key_matches_ed25519_dalek_signatures() {
+        let private_key = [3u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"message";
+        let context = b"Context 1 2 3";
+
+        let secret: SecretKey = SecretKey::from_bytes(&private_key).unwrap();
+        let public: PublicKey = (&secret).into();
+        let keypair = ed25519_dalek::Keypair { secret, public };
+        let expected = keypair
+            .sign_prehashed(Sha512::new().chain(message), Some(context))
+            .to_bytes();
+
+        let signing_key = SigningKey::from_bytes(&private_key).unwrap();
+        let signature = signing_key.sign(message, context).unwrap();
+        assert_eq!(signature.to_bytes()[..], expected[..]);
+        assert_eq!(signing_key.verifying_key().to_bytes(), public.to_bytes());
+    }
+
+    #[test]
+    fn signing_key_can_sign_many_messages() {
+        let signing_key = SigningKey::generate(&mut OsRng {});
+        let verifying_key = *signing_key.verifying_key();
+        let context = b"context";
+
+        for message in [b"first".as_ref(), b"second", b"third"].iter() {
+            let signature = signing_key.sign(message, context).unwrap();
+            assert_eq!(verifying_key.verify(message, context, &signature), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verifying_key_of_wrong_length_is_rejected() {
+        assert_eq!(
+            VerifyingKey::from_bytes(&[0u8; constants::PUBLIC_KEY_LENGTH + 1]),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn derived_public_key_report_is_clean() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let report = key.verifying_key().report();
+        assert_eq!(
+            report,
+            PublicKeyReport {
+                is_canonical: true,
+                is_small_order: false,
+                is_torsion_free: true,
+                is_identity: false,
+            }
+        );
+        assert!(!report.is_weak());
+        assert_eq!(
+            report.to_flags(),
+            PUBLIC_KEY_CANONICAL | PUBLIC_KEY_TORSION_FREE
+        );
+    }
+
+    #[test]
+    fn identity_public_key_report_is_weak() {
+        let mut identity = [0u8; constants::PUBLIC_KEY_LENGTH];
+        identity[0] = 1;
+        let report = inspect_public_key(&identity).unwrap();
+        assert!(report.is_identity && report.is_small_order && report.is_torsion_free);
+        assert!(report.is_weak());
+
+        // The same point encoded with y = p + 1.
+        let mut non_canonical = [0xffu8; constants::PUBLIC_KEY_LENGTH];
+        non_canonical[0] = 0xee;
+        non_canonical[constants::PUBLIC_KEY_LENGTH - 1] = 0x7f;
+        let report = inspect_public_key(&non_canonical).unwrap();
+        assert!(report.is_identity && !report.is_canonical);
+
+        let policy =
+            VerificationPolicy::from_flags(crate::policy::REJECT_SMALL_ORDER_KEYS).unwrap();
+        assert_eq!(
+            try_validate_public_key_with_policy(&identity, &policy),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn mixed_torsion_public_key_is_reported() {
+        let point = SigningKey::generate(&mut OsRng {}).verifying_key().point
+            + curve25519_dalek::constants::EIGHT_TORSION[2];
+        let report = inspect_public_key(point.compress().as_bytes()).unwrap();
+        assert!(report.is_canonical && !report.is_torsion_free && !report.is_weak());
+    }
+
+    #[test]
+    fn hedged_signing_reports_failing_generator() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let mut failing = RandomSource::from_fn(|_| Err(rand::Error::new("no entropy")));
+        let mut sign = |options: SigningOptions| {
+            key.sign_with_options(
+                b"message",
+                b"context",
+                SignatureMode::Ed25519ph,
+                &options,
+                &mut failing,
+            )
+        };
+
+        assert!(sign(SigningOptions::deterministic()).is_ok());
+        assert_eq!(
+            sign(SigningOptions::fault_resistant()),
+            Err(CryptoError::Unknown)
+        );
+    }
+
+    #[test]
+    fn hedged_signatures_differ_but_verify() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let sign = |options: SigningOptions| {
+            key.sign_with_options(
+                b"message",
+                b"context",
+                SignatureMode::Ed25519ph,
+                &options,
+                &mut OsRng {},
+            )
+            .unwrap()
+        };
+
+        let deterministic = sign(SigningOptions::deterministic());
+        assert_eq!(deterministic, key.sign(b"message", b"context").unwrap());
+
+        let first = sign(SigningOptions::fault_resistant());
+        let second = sign(SigningOptions::fault_resistant());
+        assert_ne!(first, second);
+        assert_ne!(first, deterministic);
+        for signature in [first, second].iter() {
+            assert_eq!(
+                key.verifying_key()
+                    .verify(b"message", b"context", signature),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn verify_after_sign_catches_faulty_signature() {
+        let mut key = SigningKey::generate(&mut OsRng {});
+        // Simulates a fault corrupting the expanded secret scalar.
+        key.scalar += Scalar::one();
+        let mut options = SigningOptions::deterministic();
+        let sign = |options: &SigningOptions| {
+            key.sign_with_options(
+                b"message",
+                b"",
+                SignatureMode::Ed25519,
+                options,
+                &mut OsRng {},
+            )
+        };
+
+        assert!(sign(&options).is_ok());
+        options.verify_after_sign = true;
+        assert_eq!(
+            sign(&options),
+            Err(CryptoError::SignatureVerificationFailure)
+        );
+    }
+
+    #[test]
+    fn small_order_key_is_refused_at_signing() {
+        // No private key expands to a small-order key, so one is made by hand.
+        let mut key = SigningKey::generate(&mut OsRng {});
+        let point = curve25519_dalek::constants::EIGHT_TORSION[1];
+        key.verifying_key = VerifyingKey {
+            compressed: point.compress(),
+            point,
+        };
+        let sign = |policy: &VerificationPolicy| {
+            key.sign_with_policy(b"message", b"", SignatureMode::Ed25519, policy)
+        };
+
+        assert!(sign(&VerificationPolicy::zip215()).is_ok());
+        let policy = VerificationPolicy::from_flags(policy::REJECT_SMALL_ORDER_KEYS).unwrap();
+        assert_eq!(sign(&policy), Err(CryptoError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn signature_made_under_strict_policy_verifies_under_it() {
+        let policy = VerificationPolicy::strict();
+        let key = SigningKey::generate(&mut OsRng {});
+        let signature = key
+            .sign_with_policy(b"message", b"", SignatureMode::Ed25519, &policy)
+            .unwrap();
+        assert_eq!(
+            key.verifying_key().verify_with_policy(
+                b"message",
+                b"",
+                &signature,
+                SignatureMode::Ed25519,
+                &policy
+            ),
+            Ok(())
+        );
+    }
 }
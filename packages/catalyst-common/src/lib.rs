@@ -4,12 +4,38 @@ extern crate catalyst_protocol_sdk_rust;
 extern crate ed25519_dalek;
 extern crate rand;
 
+pub use batch::{BatchItem, BatchRandomness, SigningEntry};
+pub use cache::VerificationKeyCache;
 pub use catalyst_protocol_sdk_rust::prelude::*;
 pub use catalyst_protocol_sdk_rust::Cryptography::{ErrorCode, SignatureBatch};
-pub(crate) use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+pub(crate) use ed25519_dalek::{PublicKey, SecretKey};
+pub use error::CryptoError;
+pub use keys::{SigningKey, VerifyingKey};
+pub use locked::LockedBox;
+pub use policy::VerificationPolicy;
+pub use rng::RandomSource;
+pub use signature::{Signature, SignatureMode};
+pub use signing::SigningOptions;
+pub use stream::{Signer, Verifier};
+pub use verifier::BatchVerifier;
+pub use wire::SignatureBatchView;
 
 pub mod batch;
+pub mod cache;
+pub mod compact;
 pub mod constants;
+pub mod error;
 pub mod extensions;
 pub mod keys;
+pub mod locked;
+pub mod policy;
+pub mod rng;
+pub mod signature;
+pub mod signing;
 pub mod std_signature;
+pub mod stream;
+pub mod verifier;
+pub mod wire;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,296 @@
+//! Allocations for long-lived secrets, such as the keypairs held by FFI
+//! handles, which are wiped when freed.
+//!
+//! With the `mlock` feature on Linux each allocation sits on pages of its own
+//! which are locked into memory, so that they are never written to swap, and
+//! left out of core dumps. The pages are surrounded by inaccessible guard
+//! pages, and the value is placed against the upper one, so that a linear
+//! overrun faults rather than reading past the secret. Otherwise the value is
+//! an ordinary heap allocation.
+
+use std::alloc::Layout;
+use std::io;
+use std::mem::{self, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::slice;
+use zeroize::Zeroize;
+
+/// A heap allocation holding a secret, wiped and released on drop.
+pub struct LockedBox<T> {
+    value: NonNull<T>,
+    // Dropped after `value`, so that the value is dropped before it is wiped.
+    region: Region,
+}
+
+impl<T> LockedBox<T> {
+    /// Moves `value` into a new allocation, failing if the pages cannot be
+    /// mapped or locked, for instance when `RLIMIT_MEMLOCK` is exhausted, or
+    /// if `T` is aligned to more than a page.
+    /// The copy of `value` passed to this call is wiped once it has been moved
+    /// into the allocation. Copies the caller made while building `value` are
+    /// not, so a secret should be created as close to this call as possible.
+    pub fn new(value: T) -> io::Result<Self> {
+        let region = Region::allocate(Layout::new::<T>())?;
+        let ptr = region.value() as *mut T;
+        let mut value = MaybeUninit::new(value);
+        unsafe { move_and_wipe(&mut value, ptr) };
+        Ok(LockedBox {
+            value: NonNull::new(ptr).expect("allocations are never null"),
+            region,
+        })
+    }
+
+    /// Whether the value sits on locked, guarded pages rather than the heap.
+    pub fn is_locked(&self) -> bool {
+        self.region.is_locked()
+    }
+}
+
+/// Moves the value held by `source` into `destination`, then wipes the bytes
+/// left behind in `source`.
+///
+/// # Safety
+///
+/// `source` must hold a value, which is no longer initialised afterwards, and
+/// `destination` must be valid for writes of one `T`.
+unsafe fn move_and_wipe<T>(source: &mut MaybeUninit<T>, destination: *mut T) {
+    ptr::copy_nonoverlapping(source.as_ptr(), destination, 1);
+    slice::from_raw_parts_mut(source.as_mut_ptr() as *mut u8, mem::size_of::<T>()).zeroize();
+}
+
+impl<T> Deref for LockedBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for LockedBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T> Drop for LockedBox<T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.value.as_ptr()) };
+    }
+}
+
+// The box owns its value as a `Box<T>` would.
+unsafe impl<T: Send> Send for LockedBox<T> {}
+unsafe impl<T: Sync> Sync for LockedBox<T> {}
+
+#[cfg(all(feature = "mlock", target_os = "linux"))]
+use self::guarded::Region;
+#[cfg(not(all(feature = "mlock", target_os = "linux")))]
+use self::heap::Region;
+
+#[cfg(all(feature = "mlock", target_os = "linux"))]
+mod guarded {
+    use std::alloc::Layout;
+    use std::io;
+    use std::ptr;
+    use std::slice;
+    use zeroize::Zeroize;
+
+    /// Locked pages between two guard pages, all unmapped on drop.
+    pub(super) struct Region {
+        base: *mut u8,
+        length: usize,
+        page_size: usize,
+        value: *mut u8,
+    }
+
+    impl Region {
+        pub(super) fn allocate(layout: Layout) -> io::Result<Self> {
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            if layout.align() > page_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Values aligned to more than a page cannot be locked.",
+                ));
+            }
+            let data_length = (layout.size().max(1) + page_size - 1) / page_size * page_size;
+            let length = data_length + 2 * page_size;
+
+            let base = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    length,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if base == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            let base = base as *mut u8;
+            let offset = (data_length - layout.size()) & !(layout.align() - 1);
+            // From here on a failure unmaps the pages as the region is dropped.
+            let region = Region {
+                base,
+                length,
+                page_size,
+                value: unsafe { base.add(page_size + offset) },
+            };
+
+            let (data, data_length) = (region.data(), region.data_length());
+            unsafe {
+                check(libc::mprotect(
+                    base as *mut libc::c_void,
+                    page_size,
+                    libc::PROT_NONE,
+                ))?;
+                check(libc::mprotect(
+                    data.add(data_length) as *mut libc::c_void,
+                    page_size,
+                    libc::PROT_NONE,
+                ))?;
+                check(libc::madvise(
+                    data as *mut libc::c_void,
+                    data_length,
+                    libc::MADV_DONTDUMP,
+                ))?;
+                check(libc::mlock(data as *const libc::c_void, data_length))?;
+            }
+            Ok(region)
+        }
+
+        pub(super) fn value(&self) -> *mut u8 {
+            self.value
+        }
+
+        pub(super) fn is_locked(&self) -> bool {
+            true
+        }
+
+        /// The start of the accessible pages between the guard pages.
+        fn data(&self) -> *mut u8 {
+            unsafe { self.base.add(self.page_size) }
+        }
+
+        fn data_length(&self) -> usize {
+            self.length - 2 * self.page_size
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            unsafe {
+                slice::from_raw_parts_mut(self.data(), self.data_length()).zeroize();
+                libc::munlock(self.data() as *const libc::c_void, self.data_length());
+                libc::munmap(self.base as *mut libc::c_void, self.length);
+            }
+        }
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(all(feature = "mlock", target_os = "linux")))]
+mod heap {
+    use std::alloc::{self, Layout};
+    use std::io;
+    use std::slice;
+    use zeroize::Zeroize;
+
+    /// An ordinary heap allocation, wiped before it is released.
+    pub(super) struct Region {
+        value: *mut u8,
+        layout: Layout,
+    }
+
+    impl Region {
+        pub(super) fn allocate(layout: Layout) -> io::Result<Self> {
+            let layout = Layout::from_size_align(layout.size().max(1), layout.align())
+                .expect("the layout of a type is always valid");
+            let value = unsafe { alloc::alloc(layout) };
+            if value.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            Ok(Region { value, layout })
+        }
+
+        pub(super) fn value(&self) -> *mut u8 {
+            self.value
+        }
+
+        pub(super) fn is_locked(&self) -> bool {
+            false
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            unsafe {
+                slice::from_raw_parts_mut(self.value, self.layout.size()).zeroize();
+                alloc::dealloc(self.value, self.layout);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn locked_box_holds_and_drops_its_value() {
+        struct Tracked(Rc<Cell<bool>>, [u8; 32]);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let dropped = Rc::new(Cell::new(false));
+        let mut locked = LockedBox::new(Tracked(dropped.clone(), [7u8; 32])).unwrap();
+        locked.1[0] = 1;
+        assert_eq!(locked.1[..2], [1, 7]);
+        assert_eq!(
+            locked.is_locked(),
+            cfg!(all(feature = "mlock", target_os = "linux"))
+        );
+        drop(locked);
+        assert!(dropped.get());
+    }
+
+    #[test]
+    fn locked_box_can_hold_a_signing_key() {
+        let key = crate::SigningKey::from_bytes(&[1u8; 32]).unwrap();
+        let public_key = key.verifying_key().to_bytes();
+        let locked = LockedBox::new(key).unwrap();
+
+        let signature = locked.sign(b"message", b"").unwrap();
+
+        assert_eq!(
+            crate::VerifyingKey::from_bytes(&public_key)
+                .unwrap()
+                .verify(b"message", b"", &signature),
+            Ok(())
+        );
+        assert_eq!(LockedBox::new(()).map(|locked| *locked).ok(), Some(()));
+    }
+
+    #[test]
+    fn moving_into_a_box_wipes_the_source() {
+        let mut source = MaybeUninit::new([7u8; 32]);
+        let mut destination = [0u8; 32];
+        unsafe { move_and_wipe(&mut source, &mut destination) };
+        assert_eq!(destination, [7u8; 32]);
+        assert_eq!(unsafe { source.assume_init() }, [0u8; 32]);
+    }
+}
@@ -0,0 +1,187 @@
+//! Runtime selection of how strictly signatures are verified.
+//!
+//! Every policy keeps the cofactored equation described in `signature`, and
+//! adds checks on the individual points and scalar of each signature. The
+//! checks do not depend on the other signatures of a batch, so single and
+//! batch verification still agree under any policy.
+
+use super::*;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::scalar::Scalar;
+
+/// Rejects weak public keys, which are those of small order.
+pub const REJECT_SMALL_ORDER_KEYS: u32 = 1;
+/// Rejects `R` and `A` unless they are the canonical encoding of their point.
+pub const REJECT_NON_CANONICAL_POINTS: u32 = 1 << 1;
+/// Rejects signatures whose `s` is not less than the group order.
+pub const REJECT_UNREDUCED_SCALARS: u32 = 1 << 2;
+/// Rejects `R` and `A` unless they lie in the prime-order subgroup.
+pub const REJECT_MIXED_TORSION: u32 = 1 << 3;
+
+const ALL_FLAGS: u32 = REJECT_SMALL_ORDER_KEYS
+    | REJECT_NON_CANONICAL_POINTS
+    | REJECT_UNREDUCED_SCALARS
+    | REJECT_MIXED_TORSION;
+
+/// The checks applied to a signature on top of the verification equation.
+/// The default is the ZIP 215 rule, which only rejects unreduced scalars.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VerificationPolicy {
+    pub reject_small_order_keys: bool,
+    pub reject_non_canonical_points: bool,
+    pub reject_unreduced_scalars: bool,
+    pub reject_mixed_torsion: bool,
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        VerificationPolicy::zip215()
+    }
+}
+
+impl VerificationPolicy {
+    /// The rule of ZIP 215, described in `signature`.
+    pub fn zip215() -> Self {
+        VerificationPolicy {
+            reject_small_order_keys: false,
+            reject_non_canonical_points: false,
+            reject_unreduced_scalars: true,
+            reject_mixed_torsion: false,
+        }
+    }
+
+    /// Applies every check, for use where signatures must be unique to their
+    /// key and message, such as consensus.
+    pub fn strict() -> Self {
+        VerificationPolicy::from_flags(ALL_FLAGS).unwrap()
+    }
+
+    /// Applies no check, accepting any `s` after reducing it modulo the group
+    /// order.
+    pub fn permissive() -> Self {
+        VerificationPolicy::from_flags(0).unwrap()
+    }
+
+    /// Builds a policy from a bitmask of the `REJECT_*` flags, failing if any
+    /// other bit is set.
+    pub fn from_flags(flags: u32) -> Option<Self> {
+        if flags & !ALL_FLAGS != 0 {
+            return None;
+        }
+        Some(VerificationPolicy {
+            reject_small_order_keys: flags & REJECT_SMALL_ORDER_KEYS != 0,
+            reject_non_canonical_points: flags & REJECT_NON_CANONICAL_POINTS != 0,
+            reject_unreduced_scalars: flags & REJECT_UNREDUCED_SCALARS != 0,
+            reject_mixed_torsion: flags & REJECT_MIXED_TORSION != 0,
+        })
+    }
+
+    pub fn to_flags(self) -> u32 {
+        let mut flags = 0;
+        if self.reject_small_order_keys {
+            flags |= REJECT_SMALL_ORDER_KEYS;
+        }
+        if self.reject_non_canonical_points {
+            flags |= REJECT_NON_CANONICAL_POINTS;
+        }
+        if self.reject_unreduced_scalars {
+            flags |= REJECT_UNREDUCED_SCALARS;
+        }
+        if self.reject_mixed_torsion {
+            flags |= REJECT_MIXED_TORSION;
+        }
+        flags
+    }
+
+    /// Decodes a signature, reducing `s` modulo the group order unless this
+    /// policy rejects unreduced scalars.
+    pub fn decode_signature(&self, bytes: &[u8]) -> Result<Signature, CryptoError> {
+        if self.reject_unreduced_scalars || bytes.len() != constants::SIGNATURE_LENGTH {
+            return Signature::from_bytes(bytes);
+        }
+        let mut lower = [0u8; 32];
+        let mut upper = [0u8; 32];
+        lower.copy_from_slice(&bytes[..32]);
+        upper.copy_from_slice(&bytes[32..]);
+        Ok(Signature {
+            R: CompressedEdwardsY(lower),
+            s: Scalar::from_bytes_mod_order(upper),
+        })
+    }
+
+    /// Checks the public key, failing with `CryptoError::InvalidPublicKey`.
+    pub fn check_public_key(&self, public_key: &VerifyingKey) -> Result<(), CryptoError> {
+        let point = public_key.point();
+        let rejected = (self.reject_small_order_keys && point.is_small_order())
+            || (self.reject_non_canonical_points
+                && point.compress().as_bytes() != public_key.as_bytes())
+            || (self.reject_mixed_torsion && !point.is_torsion_free());
+        if rejected {
+            Err(CryptoError::InvalidPublicKey)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks `R` of the signature, failing with `CryptoError::InvalidSignature`.
+    /// The scalar is checked by `decode_signature`.
+    #[allow(non_snake_case)]
+    pub fn check_signature(&self, signature: &Signature) -> Result<(), CryptoError> {
+        if !self.reject_non_canonical_points && !self.reject_mixed_torsion {
+            return Ok(());
+        }
+        let R = signature
+            .R
+            .decompress()
+            .ok_or(CryptoError::InvalidSignature)?;
+        let rejected = (self.reject_non_canonical_points && R.compress() != signature.R)
+            || (self.reject_mixed_torsion && !R.is_torsion_free());
+        if rejected {
+            Err(CryptoError::InvalidSignature)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::EIGHT_TORSION;
+
+    #[test]
+    fn policy_round_trips_through_flags() {
+        for flags in 0..=ALL_FLAGS {
+            let policy = VerificationPolicy::from_flags(flags).unwrap();
+            assert_eq!(policy.to_flags(), flags);
+        }
+        assert_eq!(VerificationPolicy::from_flags(ALL_FLAGS + 1), None);
+    }
+
+    #[test]
+    fn small_order_key_is_rejected_only_when_asked() {
+        let public_key = VerifyingKey::from_bytes(EIGHT_TORSION[1].compress().as_bytes()).unwrap();
+        assert_eq!(
+            VerificationPolicy::zip215().check_public_key(&public_key),
+            Ok(())
+        );
+        let policy = VerificationPolicy::from_flags(REJECT_SMALL_ORDER_KEYS).unwrap();
+        assert_eq!(
+            policy.check_public_key(&public_key),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn unreduced_scalar_is_reduced_when_allowed() {
+        let bytes = [0xffu8; constants::SIGNATURE_LENGTH];
+        assert_eq!(
+            VerificationPolicy::strict().decode_signature(&bytes),
+            Err(CryptoError::InvalidSignature)
+        );
+        let signature = VerificationPolicy::permissive()
+            .decode_signature(&bytes)
+            .unwrap();
+        assert_eq!(signature.s, Scalar::from_bytes_mod_order([0xffu8; 32]));
+    }
+}
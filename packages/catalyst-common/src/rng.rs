@@ -0,0 +1,166 @@
+//! Runtime selection of where randomness comes from, for hosts which supply
+//! their own entropy.
+//!
+//! Every function which needs randomness, such as
+//! `keys::generate_private_key` and `batch::verify_batch`, accepts any
+//! `CryptoRng + RngCore`, and `RandomSource` is one which can be chosen at
+//! runtime, including across the FFI.
+
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// The length of the seed of `RandomSource::insecure_from_seed`, in bytes.
+pub const SEED_LENGTH: usize = 32;
+
+/// A source of randomness chosen at runtime. The default is the operating
+/// system's generator.
+pub struct RandomSource {
+    inner: Inner,
+}
+
+/// Fills a buffer with random bytes, failing if none are available.
+type Fill = dyn FnMut(&mut [u8]) -> Result<(), Error>;
+
+enum Inner {
+    Os,
+    Fill(Box<Fill>),
+    Seeded(Box<ChaCha20Rng>),
+}
+
+impl Default for RandomSource {
+    fn default() -> Self {
+        RandomSource::os()
+    }
+}
+
+impl RandomSource {
+    /// The operating system's generator, which every function of the FFI
+    /// uses unless it is given another source.
+    pub fn os() -> Self {
+        RandomSource { inner: Inner::Os }
+    }
+
+    /// Draws bytes from `fill`, such as a host's DRBG, which must be
+    /// unpredictable. An error from `fill` is returned by `try_fill_bytes`,
+    /// and so by every function of this crate which draws from the source.
+    /// The infallible methods of `RngCore` cannot return it and panic instead.
+    pub fn from_fn<F>(fill: F) -> Self
+    where
+        F: FnMut(&mut [u8]) -> Result<(), Error> + 'static,
+    {
+        RandomSource {
+            inner: Inner::Fill(Box::new(fill)),
+        }
+    }
+
+    /// **Insecure: never use this in production.** Derives every byte from
+    /// `seed` with ChaCha20, so that test networks can be reproduced. Anyone
+    /// who learns the seed learns every key generated from it, and batch
+    /// verification with a known seed can be forged against.
+    pub fn insecure_from_seed(seed: [u8; SEED_LENGTH]) -> Self {
+        RandomSource {
+            inner: Inner::Seeded(Box::new(ChaCha20Rng::from_seed(seed))),
+        }
+    }
+
+    /// Whether the bytes come from a seed rather than from a source of
+    /// entropy.
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self.inner, Inner::Seeded(_))
+    }
+}
+
+impl RngCore for RandomSource {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("The random source failed to fill the buffer.")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        match &mut self.inner {
+            Inner::Os => OsRng.try_fill_bytes(dest),
+            Inner::Fill(fill) => fill(dest),
+            Inner::Seeded(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+// A caller supplying bytes vouches that they are unpredictable, and a seeded
+// source is marked insecure where it is created.
+impl CryptoRng for RandomSource {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{batch, keys, testing, BatchRandomness, CryptoError};
+
+    #[test]
+    fn seeded_source_is_reproducible() {
+        let key = |seed| {
+            keys::try_generate_private_key(&mut RandomSource::insecure_from_seed(seed)).unwrap()
+        };
+        assert_eq!(key([1u8; SEED_LENGTH]), key([1u8; SEED_LENGTH]));
+        assert_ne!(key([1u8; SEED_LENGTH]), key([2u8; SEED_LENGTH]));
+        assert!(RandomSource::insecure_from_seed([0u8; SEED_LENGTH]).is_deterministic());
+        assert!(!RandomSource::default().is_deterministic());
+    }
+
+    #[test]
+    fn source_draws_from_function() {
+        let mut counter = 0u8;
+        let mut source = RandomSource::from_fn(move |dest| {
+            for byte in dest.iter_mut() {
+                *byte = counter;
+                counter = counter.wrapping_add(1);
+            }
+            Ok(())
+        });
+        let mut bytes = [0u8; 4];
+        source.fill_bytes(&mut bytes);
+        assert_eq!(bytes, [0, 1, 2, 3]);
+        assert_eq!(source.next_u32(), u32::from_le_bytes([4, 5, 6, 7]));
+
+        let mut failing = RandomSource::from_fn(|_| Err(Error::new("no entropy")));
+        assert!(failing.try_fill_bytes(&mut bytes).is_err());
+        assert_eq!(
+            keys::try_generate_private_key(&mut failing),
+            Err(CryptoError::Unknown)
+        );
+        assert_eq!(
+            keys::SigningKey::try_generate(&mut failing).err(),
+            Some(CryptoError::Unknown)
+        );
+    }
+
+    #[test]
+    fn failing_source_is_reported_only_where_it_is_drawn_from() {
+        let mut failing = RandomSource::from_fn(|_| Err(Error::new("no entropy")));
+        let batch_sigs = testing::signed_batch(&[b"first", b"second"], b"context");
+
+        assert_eq!(
+            batch::try_verify_batch(&mut batch_sigs.clone(), &mut failing),
+            Err(CryptoError::Unknown)
+        );
+        assert_eq!(
+            batch::try_verify_batch_with_randomness(
+                &mut batch_sigs.clone(),
+                BatchRandomness::Transcript(b""),
+                &mut failing
+            ),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! ed25519 signatures and the RFC 8032 signature modes
+//!
+//! # Verification rule
+//!
+//! Single and batch verification accept exactly the same signatures. By
+//! default they follow the rule of [ZIP 215](https://zips.z.cash/zip-0215),
+//! to which `policy::VerificationPolicy` can add stricter checks:
+//!
+//! * `s` must be less than the group order, otherwise the signature is rejected
+//!   when it is decoded.
+//! * `A` and `R` may be any encoding of a curve point, including non-canonical
+//!   encodings and points of small order. They are hashed as they were encoded.
+//! * The signature is valid if and only if `[8][s]B = [8]R + [8][k]A`, where
+//!   `k = H(dom || R || A || M')`.
+//!
+//! As the equation is multiplied by the cofactor, a batch of valid signatures
+//! always passes, and a batch containing an invalid one fails except with
+//! negligible probability, whatever torsion components its points carry.
+
+use super::*;
+use crate::extensions::SignatureExposed;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use ed25519_dalek::{Digest, Sha512};
+
+/// The EdDSA variants defined in RFC 8032 for edwards25519.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SignatureMode {
+    /// Pure Ed25519, which signs the message itself and takes no context.
+    Ed25519 = 0,
+    /// Ed25519ctx, which signs the message itself under a non-empty context.
+    Ed25519ctx = 1,
+    /// Ed25519ph, which signs the SHA-512 hash of the message under an optional context.
+    Ed25519ph = 2,
+}
+
+impl SignatureMode {
+    /// Converts the integer representation used over the ffi into a mode.
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(SignatureMode::Ed25519),
+            1 => Some(SignatureMode::Ed25519ctx),
+            2 => Some(SignatureMode::Ed25519ph),
+            _ => None,
+        }
+    }
+
+    /// Checks the context length is allowed for this mode. Ed25519 takes no
+    /// context, Ed25519ctx requires one and Ed25519ph accepts either.
+    pub fn check_context(self, context: &[u8]) -> Result<(), CryptoError> {
+        let allowed = match self {
+            SignatureMode::Ed25519 => context.is_empty(),
+            SignatureMode::Ed25519ctx => {
+                !context.is_empty() && context.len() <= constants::CONTEXT_MAX_LENGTH
+            }
+            SignatureMode::Ed25519ph => context.len() <= constants::CONTEXT_MAX_LENGTH,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(CryptoError::InvalidContextLength)
+        }
+    }
+}
+
+/// Starts a hash over the domain separator for `mode` and `context`, which is
+/// `dom2(F, context)` from RFC 8032 for Ed25519ctx and Ed25519ph and empty for
+/// pure Ed25519.
+pub(crate) fn domain_hash(mode: SignatureMode, context: &[u8]) -> Sha512 {
+    debug_assert!(
+        mode.check_context(context).is_ok(),
+        "The context is not valid for this signature mode."
+    );
+    let mut h: Sha512 = Sha512::new();
+    let flag: u8 = match mode {
+        SignatureMode::Ed25519 => return h,
+        SignatureMode::Ed25519ctx => 0,
+        SignatureMode::Ed25519ph => 1,
+    };
+    h.input(b"SigEd25519 no Ed25519 collisions");
+    h.input([flag]);
+    h.input([context.len() as u8]);
+    h.input(context);
+    h
+}
+
+/// Computes the challenge `k = H(dom || R || A || M')` of RFC 8032, where `M'`
+/// is the SHA-512 hash of the message for Ed25519ph and the message otherwise.
+#[allow(non_snake_case)]
+pub(crate) fn challenge(
+    mode: SignatureMode,
+    context: &[u8],
+    R: &CompressedEdwardsY,
+    A: &[u8; constants::PUBLIC_KEY_LENGTH],
+    message: &[u8],
+) -> Scalar {
+    match mode {
+        SignatureMode::Ed25519ph => prehashed_challenge(context, R, A, &Sha512::digest(message)),
+        _ => Scalar::from_hash(
+            domain_hash(mode, context)
+                .chain(R.as_bytes())
+                .chain(A)
+                .chain(message),
+        ),
+    }
+}
+
+/// Computes the Ed25519ph challenge from `prehash`, the SHA-512 digest of the
+/// message.
+#[allow(non_snake_case)]
+pub(crate) fn prehashed_challenge(
+    context: &[u8],
+    R: &CompressedEdwardsY,
+    A: &[u8; constants::PUBLIC_KEY_LENGTH],
+    prehash: &[u8],
+) -> Scalar {
+    Scalar::from_hash(
+        domain_hash(SignatureMode::Ed25519ph, context)
+            .chain(R.as_bytes())
+            .chain(A)
+            .chain(prehash),
+    )
+}
+
+/// Checks the cofactored verification equation `[8][s]B = [8]R + [8][k]A`,
+/// which fails if `R` does not decompress.
+#[allow(non_snake_case)]
+pub(crate) fn equation_holds(
+    R: &CompressedEdwardsY,
+    s: &Scalar,
+    A: &EdwardsPoint,
+    k: &Scalar,
+) -> bool {
+    match R.decompress() {
+        Some(R) => (EdwardsPoint::vartime_double_scalar_mul_basepoint(k, &-A, s) - R)
+            .mul_by_cofactor()
+            .is_identity(),
+        None => false,
+    }
+}
+
+/// A decoded ed25519 signature, consisting of the point `R` and the scalar `s`.
+#[allow(non_snake_case)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub(crate) R: CompressedEdwardsY,
+    pub(crate) s: Scalar,
+}
+
+impl Signature {
+    /// Decodes a signature, failing if it is not `constants::SIGNATURE_LENGTH`
+    /// bytes long or if `s` is not reduced modulo the group order.
+    #[allow(non_snake_case)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CryptoError> {
+        if bytes.len() != constants::SIGNATURE_LENGTH {
+            return Err(CryptoError::InvalidSignature);
+        }
+        let mut lower = [0u8; 32];
+        let mut upper = [0u8; 32];
+        lower.copy_from_slice(&bytes[..32]);
+        upper.copy_from_slice(&bytes[32..]);
+
+        let s = Scalar::from_canonical_bytes(upper).ok_or(CryptoError::InvalidSignature)?;
+        Ok(Signature {
+            R: CompressedEdwardsY(lower),
+            s,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; constants::SIGNATURE_LENGTH] {
+        let mut bytes = [0u8; constants::SIGNATURE_LENGTH];
+        bytes[..32].copy_from_slice(self.R.as_bytes());
+        bytes[32..].copy_from_slice(self.s.as_bytes());
+        bytes
+    }
+}
+
+impl From<Signature> for SignatureExposed {
+    fn from(sig: Signature) -> Self {
+        SignatureExposed { R: sig.R, s: sig.s }
+    }
+}
+
+impl From<SignatureExposed> for Signature {
+    fn from(sig: SignatureExposed) -> Self {
+        Signature { R: sig.R, s: sig.s }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_length_is_checked_per_mode() {
+        let long_context = [0u8; constants::CONTEXT_MAX_LENGTH + 1];
+        assert_eq!(SignatureMode::Ed25519.check_context(b""), Ok(()));
+        assert_eq!(
+            SignatureMode::Ed25519.check_context(b"context"),
+            Err(CryptoError::InvalidContextLength)
+        );
+        assert_eq!(
+            SignatureMode::Ed25519ctx.check_context(b""),
+            Err(CryptoError::InvalidContextLength)
+        );
+        assert_eq!(SignatureMode::Ed25519ctx.check_context(b"context"), Ok(()));
+        assert_eq!(SignatureMode::Ed25519ph.check_context(b""), Ok(()));
+        assert_eq!(
+            SignatureMode::Ed25519ph.check_context(&long_context),
+            Err(CryptoError::InvalidContextLength)
+        );
+    }
+
+    #[test]
+    fn mode_round_trips_through_integer() {
+        for mode in [
+            SignatureMode::Ed25519,
+            SignatureMode::Ed25519ctx,
+            SignatureMode::Ed25519ph,
+        ]
+        .iter()
+        {
+            assert_eq!(SignatureMode::from_i32(*mode as i32), Some(*mode));
+        }
+        assert_eq!(SignatureMode::from_i32(3), None);
+    }
+
+    #[test]
+    fn signature_round_trips_through_bytes() {
+        let mut bytes = [7u8; constants::SIGNATURE_LENGTH];
+        bytes[constants::SIGNATURE_LENGTH - 1] = 0;
+        let signature = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!(signature.to_bytes()[..], bytes[..]);
+    }
+
+    #[test]
+    fn signature_with_unreduced_scalar_is_rejected() {
+        let bytes = [0xffu8; constants::SIGNATURE_LENGTH];
+        assert_eq!(
+            Signature::from_bytes(&bytes),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn signature_of_wrong_length_is_rejected() {
+        assert_eq!(
+            Signature::from_bytes(&[0u8; constants::SIGNATURE_LENGTH - 1]),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Runtime selection of how signatures are made.
+//!
+//! RFC 8032 derives the nonce `r` of a signature deterministically from the
+//! private key and the message. A fault injected while signing the same
+//! message twice then yields two signatures which share `r` but not `k`,
+//! from which the private key follows. Hedged signing also hashes fresh
+//! randomness into `r`, as in draft-irtf-cfrg-det-sigs-with-noise, so that no
+//! two signatures share a nonce. The signatures are verified as any others,
+//! and a random number generator whose output is predictable leaves them as
+//! safe as deterministic ones. A generator which reports an error fails the
+//! signing instead, as no signature is made without the noise requested.
+
+/// Mixes fresh randomness into the nonce of each signature.
+pub const HEDGED_NONCE: u32 = 1;
+/// Verifies each signature against the signing key's public key before it is
+/// returned, so that a faulty signature is never released.
+pub const VERIFY_AFTER_SIGN: u32 = 1 << 1;
+
+const ALL_FLAGS: u32 = HEDGED_NONCE | VERIFY_AFTER_SIGN;
+
+/// The countermeasures applied while signing. The default is the
+/// deterministic signing of RFC 8032 without a self check.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SigningOptions {
+    pub hedged_nonce: bool,
+    pub verify_after_sign: bool,
+}
+
+impl SigningOptions {
+    /// Signs exactly as RFC 8032 does.
+    pub fn deterministic() -> Self {
+        SigningOptions::default()
+    }
+
+    /// Applies every countermeasure, for signers such as validators whose
+    /// hardware may be open to fault injection.
+    pub fn fault_resistant() -> Self {
+        SigningOptions::from_flags(ALL_FLAGS).unwrap()
+    }
+
+    /// Builds options from a bitmask of the flags above, failing if any other
+    /// bit is set.
+    pub fn from_flags(flags: u32) -> Option<Self> {
+        if flags & !ALL_FLAGS != 0 {
+            return None;
+        }
+        Some(SigningOptions {
+            hedged_nonce: flags & HEDGED_NONCE != 0,
+            verify_after_sign: flags & VERIFY_AFTER_SIGN != 0,
+        })
+    }
+
+    pub fn to_flags(self) -> u32 {
+        let mut flags = 0;
+        if self.hedged_nonce {
+            flags |= HEDGED_NONCE;
+        }
+        if self.verify_after_sign {
+            flags |= VERIFY_AFTER_SIGN;
+        }
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_round_trip_through_flags() {
+        for flags in 0..=ALL_FLAGS {
+            assert_eq!(
+                SigningOptions::from_flags(flags).map(SigningOptions::to_flags),
+                Some(flags)
+            );
+        }
+        assert_eq!(SigningOptions::from_flags(1 << 2), None);
+        assert_eq!(SigningOptions::deterministic().to_flags(), 0);
+        assert_eq!(SigningOptions::fault_resistant().to_flags(), ALL_FLAGS);
+    }
+}
@@ -1,7 +1,8 @@
-//! ed25519ph signature and verification
+//! ed25519ph signature and verification, along with the pure Ed25519 and
+//! Ed25519ctx variants of RFC 8032
 
 use super::*;
-use ed25519_dalek::{Digest, Sha512, Signature};
+use rand::{CryptoRng, RngCore};
 
 #[inline]
 pub fn sign(
@@ -9,54 +10,336 @@ pub fn sign(
     public_key: &mut [u8; constants::PUBLIC_KEY_LENGTH],
     private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
     message: &[u8],
-    context: &'static [u8],
+    context: &[u8],
 ) -> i32 {
-    let private_key = match SecretKey::from_bytes(private_key) {
-        Ok(private_key) => private_key,
-        Err(_) => return ErrorCode::INVALID_PRIVATE_KEY.value(),
-    };
-    let public = PublicKey::from(&private_key);
-    let keypair: Keypair = Keypair {
-        secret: private_key,
-        public,
-    };
+    error::to_error_code(
+        try_sign(private_key, message, context).map(|(sig, public)| {
+            signature.copy_from_slice(&sig);
+            public_key.copy_from_slice(&public);
+        }),
+    )
+}
 
-    if context.len() > constants::CONTEXT_MAX_LENGTH {
-        return ErrorCode::INVALID_CONTEXT_LENGTH.value();
-    }
+/// Signs the message under the given context, returning the signature along
+/// with the public key corresponding to `private_key`.
+#[inline]
+pub fn try_sign(
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    message: &[u8],
+    context: &[u8],
+) -> Result<
+    (
+        [u8; constants::SIGNATURE_LENGTH],
+        [u8; constants::PUBLIC_KEY_LENGTH],
+    ),
+    CryptoError,
+> {
+    try_sign_with_mode(private_key, message, context, SignatureMode::Ed25519ph)
+}
 
-    let mut prehashed: Sha512 = Sha512::new();
-    prehashed.input(message);
-    let sig = keypair.sign_prehashed(prehashed, Some(context)).to_bytes();
+#[inline]
+pub fn sign_with_mode(
+    signature: &mut [u8; constants::SIGNATURE_LENGTH],
+    public_key: &mut [u8; constants::PUBLIC_KEY_LENGTH],
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+) -> i32 {
+    error::to_error_code(try_sign_with_mode(private_key, message, context, mode).map(
+        |(sig, public)| {
+            signature.copy_from_slice(&sig);
+            public_key.copy_from_slice(&public);
+        },
+    ))
+}
 
-    signature.copy_from_slice(&sig);
-    public_key.copy_from_slice(&public.to_bytes());
+/// Signs the message using the RFC 8032 variant `mode`, returning the
+/// signature along with the public key corresponding to `private_key`.
+#[inline]
+pub fn try_sign_with_mode(
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+) -> Result<
+    (
+        [u8; constants::SIGNATURE_LENGTH],
+        [u8; constants::PUBLIC_KEY_LENGTH],
+    ),
+    CryptoError,
+> {
+    let signing_key = SigningKey::from_bytes(private_key)?;
+    let signature = signing_key.sign_with_mode(message, context, mode)?;
+    Ok((signature.to_bytes(), signing_key.verifying_key().to_bytes()))
+}
 
-    ErrorCode::NO_ERROR.value()
+#[inline]
+pub fn sign_with_policy(
+    signature: &mut [u8; constants::SIGNATURE_LENGTH],
+    public_key: &mut [u8; constants::PUBLIC_KEY_LENGTH],
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+    policy: &VerificationPolicy,
+) -> i32 {
+    error::to_error_code(
+        try_sign_with_policy(private_key, message, context, mode, policy).map(|(sig, public)| {
+            signature.copy_from_slice(&sig);
+            public_key.copy_from_slice(&public);
+        }),
+    )
 }
 
+/// Signs the message using the RFC 8032 variant `mode`, first checking the
+/// public key corresponding to `private_key` against `policy`, and returns
+/// the signature along with that public key.
 #[inline]
-pub fn verify(signature: &[u8], public_key: &[u8], message: &[u8], context: &'static [u8]) -> i32 {
-    let public_key = match PublicKey::from_bytes(public_key) {
-        Ok(public_key) => public_key,
-        Err(_) => return ErrorCode::INVALID_PUBLIC_KEY.value(),
-    };
-    let signature = match Signature::from_bytes(signature) {
-        Ok(signature) => signature,
-        Err(_) => return ErrorCode::INVALID_SIGNATURE.value(),
-    };
-    let mut prehashed: Sha512 = Sha512::new();
-    prehashed.input(message);
+pub fn try_sign_with_policy(
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+    policy: &VerificationPolicy,
+) -> Result<
+    (
+        [u8; constants::SIGNATURE_LENGTH],
+        [u8; constants::PUBLIC_KEY_LENGTH],
+    ),
+    CryptoError,
+> {
+    let signing_key = SigningKey::from_bytes(private_key)?;
+    let signature = signing_key.sign_with_policy(message, context, mode, policy)?;
+    Ok((signature.to_bytes(), signing_key.verifying_key().to_bytes()))
+}
 
-    match public_key.verify_prehashed(prehashed, Some(context), &signature) {
-        Ok(_) => ErrorCode::NO_ERROR.value(),
-        Err(_) => ErrorCode::SIGNATURE_VERIFICATION_FAILURE.value(),
-    }
+/// Signs the message using the RFC 8032 variant `mode` and the countermeasures
+/// of `options`, returning the signature along with the public key
+/// corresponding to `private_key`.
+#[inline]
+pub fn try_sign_with_options<T>(
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+    options: &SigningOptions,
+    csprng: &mut T,
+) -> Result<
+    (
+        [u8; constants::SIGNATURE_LENGTH],
+        [u8; constants::PUBLIC_KEY_LENGTH],
+    ),
+    CryptoError,
+>
+where
+    T: CryptoRng + RngCore,
+{
+    let signing_key = SigningKey::from_bytes(private_key)?;
+    let signature = signing_key.sign_with_options(message, context, mode, options, csprng)?;
+    Ok((signature.to_bytes(), signing_key.verifying_key().to_bytes()))
+}
+
+#[inline]
+pub fn sign_prehashed(
+    signature: &mut [u8; constants::SIGNATURE_LENGTH],
+    public_key: &mut [u8; constants::PUBLIC_KEY_LENGTH],
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    prehash: &[u8; constants::PREHASH_LENGTH],
+    context: &[u8],
+) -> i32 {
+    error::to_error_code(
+        try_sign_prehashed(private_key, prehash, context).map(|(sig, public)| {
+            signature.copy_from_slice(&sig);
+            public_key.copy_from_slice(&public);
+        }),
+    )
+}
+
+/// Signs the SHA-512 digest of a message, making the signature `try_sign`
+/// would make over the message itself.
+#[inline]
+pub fn try_sign_prehashed(
+    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+    prehash: &[u8; constants::PREHASH_LENGTH],
+    context: &[u8],
+) -> Result<
+    (
+        [u8; constants::SIGNATURE_LENGTH],
+        [u8; constants::PUBLIC_KEY_LENGTH],
+    ),
+    CryptoError,
+> {
+    let signing_key = SigningKey::from_bytes(private_key)?;
+    let signature = signing_key.sign_prehashed(prehash, context)?;
+    Ok((signature.to_bytes(), signing_key.verifying_key().to_bytes()))
+}
+
+#[inline]
+pub fn verify(signature: &[u8], public_key: &[u8], message: &[u8], context: &[u8]) -> i32 {
+    error::to_error_code(try_verify(signature, public_key, message, context))
+}
+
+/// Verifies that the signature was made over the message and context by the
+/// holder of the private key corresponding to `public_key`.
+#[inline]
+pub fn try_verify(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+) -> Result<(), CryptoError> {
+    try_verify_with_mode(
+        signature,
+        public_key,
+        message,
+        context,
+        SignatureMode::Ed25519ph,
+    )
+}
+
+#[inline]
+pub fn verify_prehashed(
+    signature: &[u8],
+    public_key: &[u8],
+    prehash: &[u8; constants::PREHASH_LENGTH],
+    context: &[u8],
+) -> i32 {
+    error::to_error_code(try_verify_prehashed(
+        signature, public_key, prehash, context,
+    ))
+}
+
+/// Verifies a signature given the SHA-512 digest of the message, as
+/// `try_verify` would verify it over the message itself.
+#[inline]
+pub fn try_verify_prehashed(
+    signature: &[u8],
+    public_key: &[u8],
+    prehash: &[u8; constants::PREHASH_LENGTH],
+    context: &[u8],
+) -> Result<(), CryptoError> {
+    let public_key = VerifyingKey::from_bytes(public_key)?;
+    let signature = Signature::from_bytes(signature)?;
+    public_key.verify_prehashed(prehash, context, &signature)
+}
+
+#[inline]
+pub fn verify_prehashed_with_policy(
+    signature: &[u8],
+    public_key: &[u8],
+    prehash: &[u8; constants::PREHASH_LENGTH],
+    context: &[u8],
+    policy: &VerificationPolicy,
+) -> i32 {
+    error::to_error_code(try_verify_prehashed_with_policy(
+        signature, public_key, prehash, context, policy,
+    ))
+}
+
+/// As `try_verify_prehashed`, decoding and checking the signature and public
+/// key according to `policy`.
+#[inline]
+pub fn try_verify_prehashed_with_policy(
+    signature: &[u8],
+    public_key: &[u8],
+    prehash: &[u8; constants::PREHASH_LENGTH],
+    context: &[u8],
+    policy: &VerificationPolicy,
+) -> Result<(), CryptoError> {
+    let public_key = VerifyingKey::from_bytes(public_key)?;
+    let signature = policy.decode_signature(signature)?;
+    public_key.verify_prehashed_with_policy(prehash, context, &signature, policy)
+}
+
+#[inline]
+pub fn verify_with_mode(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+) -> i32 {
+    error::to_error_code(try_verify_with_mode(
+        signature, public_key, message, context, mode,
+    ))
+}
+
+/// Verifies a signature made using the RFC 8032 variant `mode`.
+#[inline]
+pub fn try_verify_with_mode(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+) -> Result<(), CryptoError> {
+    let public_key = VerifyingKey::from_bytes(public_key)?;
+    let signature = Signature::from_bytes(signature)?;
+    public_key.verify_with_mode(message, context, &signature, mode)
+}
+
+#[inline]
+pub fn verify_with_cache(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+    cache: &mut VerificationKeyCache,
+) -> i32 {
+    error::to_error_code(try_verify_with_cache(
+        signature, public_key, message, context, cache,
+    ))
+}
+
+/// As `try_verify`, taking the decompressed public key from `cache`.
+#[inline]
+pub fn try_verify_with_cache(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+    cache: &mut VerificationKeyCache,
+) -> Result<(), CryptoError> {
+    let public_key = cache.get(public_key)?;
+    let signature = Signature::from_bytes(signature)?;
+    public_key.verify(message, context, &signature)
+}
+
+#[inline]
+pub fn verify_with_policy(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+    policy: &VerificationPolicy,
+) -> i32 {
+    error::to_error_code(try_verify_with_policy(
+        signature, public_key, message, context, mode, policy,
+    ))
+}
+
+/// Verifies a signature made using the RFC 8032 variant `mode`, decoding and
+/// checking the signature and public key according to `policy`.
+#[inline]
+pub fn try_verify_with_policy(
+    signature: &[u8],
+    public_key: &[u8],
+    message: &[u8],
+    context: &[u8],
+    mode: SignatureMode,
+    policy: &VerificationPolicy,
+) -> Result<(), CryptoError> {
+    let public_key = VerifyingKey::from_bytes(public_key)?;
+    let signature = policy.decode_signature(signature)?;
+    public_key.verify_with_policy(message, context, &signature, mode, policy)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ed25519_dalek::{Digest, Sha512};
     use hex::FromHex;
 
     #[test]
@@ -175,4 +458,316 @@ mod tests {
 
         assert_eq!(public_key, public_key2);
     }
+
+    #[test]
+    fn try_sign_output_verifies_with_try_verify() {
+        let private_key = [1u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"message";
+        let context = b"Context 1 2 3";
+
+        let (sig, public_key) = try_sign(&private_key, message, context).unwrap();
+        assert_eq!(try_verify(&sig, &public_key, message, context), Ok(()));
+        assert_eq!(
+            try_verify(&sig, &public_key, b"another message", context),
+            Err(CryptoError::SignatureVerificationFailure)
+        );
+    }
+
+    #[test]
+    fn try_verify_rejects_malformed_signature() {
+        let public_key =
+            keys::try_publickey_from_private(&[0u8; constants::PRIVATE_KEY_LENGTH]).unwrap();
+        assert_eq!(
+            try_verify(&[0u8; 10], &public_key, b"message", b""),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+
+    /// Signs with the RFC 8032 secret key and checks both the derived public
+    /// key and signature against the test vector, then verifies it.
+    fn check_rfc8032_vector(
+        mode: SignatureMode,
+        private_key: &str,
+        public_key: &str,
+        message: &str,
+        context: &str,
+        signature: &str,
+    ) {
+        let private_key = <[u8; constants::PRIVATE_KEY_LENGTH]>::from_hex(private_key).unwrap();
+        let public_key = <[u8; constants::PUBLIC_KEY_LENGTH]>::from_hex(public_key).unwrap();
+        let message = Vec::from_hex(message).unwrap();
+        let context = Vec::from_hex(context).unwrap();
+        let signature = Vec::from_hex(signature).unwrap();
+
+        let mut out_sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut out_public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        assert_eq!(
+            sign_with_mode(
+                &mut out_sig,
+                &mut out_public_key,
+                &private_key,
+                &message,
+                &context,
+                mode
+            ),
+            ErrorCode::NO_ERROR.value()
+        );
+        assert_eq!(out_public_key, public_key);
+        assert_eq!(out_sig[..], signature[..]);
+        assert_eq!(
+            verify_with_mode(&signature, &public_key, &message, &context, mode),
+            ErrorCode::NO_ERROR.value()
+        );
+    }
+
+    #[test]
+    fn can_sign_and_verify_rfc8032_ed25519_test_vectors() {
+        check_rfc8032_vector(
+            SignatureMode::Ed25519,
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        );
+        check_rfc8032_vector(
+            SignatureMode::Ed25519,
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        );
+        check_rfc8032_vector(
+            SignatureMode::Ed25519,
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "",
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        );
+    }
+
+    #[test]
+    fn can_sign_and_verify_rfc8032_ed25519ctx_test_vectors() {
+        let private_key = "0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6";
+        let public_key = "dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292";
+        check_rfc8032_vector(
+            SignatureMode::Ed25519ctx,
+            private_key,
+            public_key,
+            "f726936d19c800494e3fdaff20b276a8",
+            "666f6f",
+            "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d",
+        );
+        check_rfc8032_vector(
+            SignatureMode::Ed25519ctx,
+            private_key,
+            public_key,
+            "f726936d19c800494e3fdaff20b276a8",
+            "626172",
+            "fc60d5872fc46b3aa69f8b5b4351d5808f92bcc044606db097abab6dbcb1aee3216c48e8b3b66431b5b186d1d28f8ee15a5ca2df6668346291c2043d4eb3e90d",
+        );
+        check_rfc8032_vector(
+            SignatureMode::Ed25519ctx,
+            private_key,
+            public_key,
+            "508e9e6882b979fea900f62adceaca35",
+            "666f6f",
+            "8b70c1cc8310e1de20ac53ce28ae6e7207f33c3295e03bb5c0732a1d20dc64908922a8b052cf99b7c4fe107a5abb5b2c4085ae75890d02df26269d8945f84b0b",
+        );
+        check_rfc8032_vector(
+            SignatureMode::Ed25519ctx,
+            "ab9c2853ce297ddab85c993b3ae14bcad39b2c682beabc27d6d4eb20711d6560",
+            "0f1d1274943b91415889152e893d80e93275a1fc0b65fd71b4b0dda10ad7d772",
+            "f726936d19c800494e3fdaff20b276a8",
+            "666f6f",
+            "21655b5f1aa965996b3f97b3c849eafba922a0a62992f73b3d1b73106a84ad85e9b86a7b6005ea868337ff2d20a7f5fbd4cd10b0be49a68da2b2e0dc0ad8960f",
+        );
+    }
+
+    #[test]
+    fn can_sign_and_verify_rfc8032_ed25519ph_test_vector() {
+        check_rfc8032_vector(
+            SignatureMode::Ed25519ph,
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+            "616263",
+            "",
+            "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406",
+        );
+    }
+
+    #[test]
+    fn can_sign_and_verify_rfc8032_ed25519ph_test_vector_from_prehash() {
+        let private_key = <[u8; constants::PRIVATE_KEY_LENGTH]>::from_hex(
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+        )
+        .unwrap();
+        let expected_public_key = <[u8; constants::PUBLIC_KEY_LENGTH]>::from_hex(
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+        )
+        .unwrap();
+        let expected_signature = Vec::from_hex("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406").unwrap();
+        let mut prehash = [0u8; constants::PREHASH_LENGTH];
+        prehash.copy_from_slice(&Sha512::digest(b"abc"));
+
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        assert_eq!(
+            sign_prehashed(&mut signature, &mut public_key, &private_key, &prehash, b""),
+            ErrorCode::NO_ERROR.value()
+        );
+        assert_eq!(signature.to_vec(), expected_signature);
+        assert_eq!(public_key, expected_public_key);
+        assert_eq!(
+            verify_prehashed(&signature, &public_key, &prehash, b""),
+            ErrorCode::NO_ERROR.value()
+        );
+        assert_eq!(
+            verify(&signature, &public_key, b"abc", b""),
+            ErrorCode::NO_ERROR.value()
+        );
+
+        prehash[0] ^= 1;
+        assert_eq!(
+            try_verify_prehashed(&signature, &public_key, &prehash, b""),
+            Err(CryptoError::SignatureVerificationFailure)
+        );
+    }
+
+    #[test]
+    fn verify_prehashed_with_policy_applies_policy_checks() {
+        // The identity as both `R` and `A` with `s` of zero satisfies the
+        // equation for any message.
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        public_key[0] = 1;
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        signature[0] = 1;
+        let prehash = [7u8; constants::PREHASH_LENGTH];
+        let verify = |policy: VerificationPolicy| {
+            try_verify_prehashed_with_policy(&signature, &public_key, &prehash, b"", &policy)
+        };
+
+        assert_eq!(verify(VerificationPolicy::default()), Ok(()));
+        assert_eq!(
+            verify(VerificationPolicy::default()),
+            try_verify_prehashed(&signature, &public_key, &prehash, b"")
+        );
+        assert_eq!(
+            verify(VerificationPolicy::strict()),
+            Err(CryptoError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn signature_made_with_policy_verifies_under_it() {
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let policy = VerificationPolicy::strict();
+        let (sig, public_key) = try_sign_with_policy(
+            &private_key,
+            b"message",
+            b"context",
+            SignatureMode::Ed25519ctx,
+            &policy,
+        )
+        .unwrap();
+
+        assert_eq!(
+            try_sign_with_mode(
+                &private_key,
+                b"message",
+                b"context",
+                SignatureMode::Ed25519ctx
+            ),
+            Ok((sig, public_key))
+        );
+        assert_eq!(
+            try_verify_with_policy(
+                &sig,
+                &public_key,
+                b"message",
+                b"context",
+                SignatureMode::Ed25519ctx,
+                &policy
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn verification_fails_for_different_signature_mode() {
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"message";
+        let context = b"context";
+        let (sig, public_key) =
+            try_sign_with_mode(&private_key, message, context, SignatureMode::Ed25519ctx).unwrap();
+
+        assert_eq!(
+            try_verify_with_mode(
+                &sig,
+                &public_key,
+                message,
+                context,
+                SignatureMode::Ed25519ph
+            ),
+            Err(CryptoError::SignatureVerificationFailure)
+        );
+    }
+
+    #[test]
+    fn pure_ed25519_rejects_non_empty_context() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let result = sign_with_mode(
+            &mut sig,
+            &mut public_key,
+            &private_key,
+            b"message",
+            b"context",
+            SignatureMode::Ed25519,
+        );
+        assert_eq!(result, ErrorCode::INVALID_CONTEXT_LENGTH.value());
+    }
+
+    #[test]
+    fn can_sign_and_verify_with_context_borrowed_from_heap() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"message";
+        let context = String::from("Context 1 2 3").into_bytes();
+
+        assert_eq!(
+            sign(&mut sig, &mut public_key, &private_key, message, &context),
+            ErrorCode::NO_ERROR.value()
+        );
+        assert_eq!(
+            verify(&sig, &public_key, message, &context),
+            ErrorCode::NO_ERROR.value()
+        );
+    }
+
+    #[test]
+    fn verify_with_cache_matches_verify() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let context = b"context";
+        sign(&mut sig, &mut public_key, &private_key, b"message", context);
+        let mut cache = VerificationKeyCache::new(8);
+
+        for _ in 0..2 {
+            assert_eq!(
+                try_verify_with_cache(&sig, &public_key, b"message", context, &mut cache),
+                Ok(())
+            );
+            assert_eq!(
+                try_verify_with_cache(&sig, &public_key, b"other message", context, &mut cache),
+                Err(CryptoError::SignatureVerificationFailure)
+            );
+        }
+        assert_eq!(cache.len(), 1);
+    }
 }
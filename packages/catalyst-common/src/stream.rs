@@ -0,0 +1,243 @@
+//! Ed25519ph signing and verification of messages which are hashed in
+//! pieces as they are read, such as large files, rather than held in memory.
+//!
+//! Ed25519ph signs the SHA-512 digest of the message, so a signature made by
+//! a `Signer` is the one `SigningKey::sign` makes over the whole message, and
+//! either can be checked by a `Verifier` or by `VerifyingKey::verify`.
+
+use super::*;
+use ed25519_dalek::{Digest, Sha512};
+use std::io::{self, Read, Write};
+
+/// Hashes a message as it is given and signs it once it is complete.
+#[derive(Clone)]
+pub struct Signer {
+    prehash: Prehash,
+}
+
+impl Signer {
+    /// Starts a message to be signed under `context`, failing if the context
+    /// is too long.
+    pub fn new(context: &[u8]) -> Result<Self, CryptoError> {
+        Ok(Signer {
+            prehash: Prehash::new(context)?,
+        })
+    }
+
+    /// Appends `data` to the message.
+    pub fn update(&mut self, data: &[u8]) {
+        self.prehash.hash.input(data);
+    }
+
+    /// Appends everything read from `reader` to the message, returning the
+    /// number of bytes read.
+    pub fn update_reader<R: Read>(&mut self, reader: &mut R) -> io::Result<u64> {
+        io::copy(reader, self)
+    }
+
+    /// Signs the message given so far with `key`.
+    pub fn finalize_sign(self, key: &SigningKey) -> Result<Signature, CryptoError> {
+        key.sign_prehashed(&self.prehash.digest(), &self.prehash.context)
+    }
+}
+
+impl Write for Signer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hashes a message as it is given and verifies a signature over it once it
+/// is complete.
+#[derive(Clone)]
+pub struct Verifier {
+    prehash: Prehash,
+}
+
+impl Verifier {
+    /// Starts a message whose signature was made under `context`, failing if
+    /// the context is too long.
+    pub fn new(context: &[u8]) -> Result<Self, CryptoError> {
+        Ok(Verifier {
+            prehash: Prehash::new(context)?,
+        })
+    }
+
+    /// Appends `data` to the message.
+    pub fn update(&mut self, data: &[u8]) {
+        self.prehash.hash.input(data);
+    }
+
+    /// Appends everything read from `reader` to the message, returning the
+    /// number of bytes read.
+    pub fn update_reader<R: Read>(&mut self, reader: &mut R) -> io::Result<u64> {
+        io::copy(reader, self)
+    }
+
+    /// Verifies that `signature` was made over the message given so far by
+    /// the holder of the signing key corresponding to `public_key`.
+    pub fn finalize_verify(
+        self,
+        public_key: &VerifyingKey,
+        signature: &Signature,
+    ) -> Result<(), CryptoError> {
+        public_key.verify_prehashed(&self.prehash.digest(), &self.prehash.context, signature)
+    }
+
+    /// Verifies an encoded signature against an encoded public key as
+    /// `finalize_verify` does, decoding and checking both under the default
+    /// `VerificationPolicy` as `std_signature` does for whole messages.
+    pub fn finalize_verify_bytes(
+        self,
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), CryptoError> {
+        std_signature::try_verify_prehashed_with_policy(
+            signature,
+            public_key,
+            &self.prehash.digest(),
+            &self.prehash.context,
+            &VerificationPolicy::default(),
+        )
+    }
+}
+
+impl Write for Verifier {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The context together with the SHA-512 state of the message hashed so far.
+#[derive(Clone)]
+struct Prehash {
+    context: Vec<u8>,
+    hash: Sha512,
+}
+
+impl Prehash {
+    fn new(context: &[u8]) -> Result<Self, CryptoError> {
+        SignatureMode::Ed25519ph.check_context(context)?;
+        Ok(Prehash {
+            context: context.to_vec(),
+            hash: Sha512::new(),
+        })
+    }
+
+    fn digest(&self) -> [u8; constants::PREHASH_LENGTH] {
+        let mut prehash = [0u8; constants::PREHASH_LENGTH];
+        prehash.copy_from_slice(&self.hash.clone().result());
+        prehash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn streamed_signature_matches_whole_message_signature() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let message: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let context = b"snapshot";
+
+        let mut signer = Signer::new(context).unwrap();
+        for chunk in message.chunks(777) {
+            signer.update(chunk);
+        }
+        let signature = signer.finalize_sign(&key).unwrap();
+
+        assert_eq!(signature, key.sign(&message, context).unwrap());
+        assert_eq!(
+            key.verifying_key().verify(&message, context, &signature),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn streamed_verification_accepts_only_the_signed_message() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let message = vec![7u8; 5000];
+        let context = b"ledger";
+        let signature = key.sign(&message, context).unwrap();
+
+        let mut verifier = Verifier::new(context).unwrap();
+        assert_eq!(
+            verifier.update_reader(&mut message.as_slice()).unwrap(),
+            5000
+        );
+        assert_eq!(
+            verifier
+                .clone()
+                .finalize_verify(key.verifying_key(), &signature),
+            Ok(())
+        );
+
+        verifier.update(b"appended");
+        assert_eq!(
+            verifier.finalize_verify(key.verifying_key(), &signature),
+            Err(CryptoError::SignatureVerificationFailure)
+        );
+
+        let mut verifier = Verifier::new(b"other").unwrap();
+        verifier.update(&message);
+        assert_eq!(
+            verifier.finalize_verify(key.verifying_key(), &signature),
+            Err(CryptoError::SignatureVerificationFailure)
+        );
+    }
+
+    #[test]
+    fn streamed_verification_of_encoded_signature_applies_policy() {
+        let key = SigningKey::generate(&mut OsRng {});
+        let signature = key.sign(b"message", b"").unwrap().to_bytes();
+        let public_key = key.verifying_key().to_bytes();
+        let mut verifier = Verifier::new(b"").unwrap();
+        verifier.update(b"message");
+
+        assert_eq!(
+            verifier
+                .clone()
+                .finalize_verify_bytes(&signature, &public_key),
+            Ok(())
+        );
+        assert_eq!(
+            verifier
+                .clone()
+                .finalize_verify_bytes(&signature[1..], &public_key),
+            Err(CryptoError::InvalidSignature)
+        );
+
+        // An `s` of at least 2^255 is never reduced, so the policy rejects it.
+        let mut unreduced = signature;
+        unreduced[constants::SIGNATURE_LENGTH - 1] = 0xff;
+        assert_eq!(
+            verifier.finalize_verify_bytes(&unreduced, &public_key),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn context_is_checked_when_starting() {
+        let context = [0u8; constants::CONTEXT_MAX_LENGTH + 1];
+        assert!(matches!(
+            Signer::new(&context),
+            Err(CryptoError::InvalidContextLength)
+        ));
+        assert!(matches!(
+            Verifier::new(&context),
+            Err(CryptoError::InvalidContextLength)
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+//! Fixtures shared by the unit tests of this crate.
+
+use super::*;
+use rand::rngs::OsRng;
+
+/// Signs each of `messages` under `context` with a freshly generated key,
+/// returning the signatures together with the keys which verify them.
+pub fn signed_messages<M: AsRef<[u8]>>(
+    messages: &[M],
+    context: &[u8],
+) -> (Vec<Signature>, Vec<VerifyingKey>) {
+    let mut signatures = Vec::new();
+    let mut public_keys = Vec::new();
+    for message in messages.iter() {
+        let key = SigningKey::generate(&mut OsRng {});
+        signatures.push(key.sign(message.as_ref(), context).unwrap());
+        public_keys.push(*key.verifying_key());
+    }
+    (signatures, public_keys)
+}
+
+/// Signs each of `messages` under `context` as `signed_messages` does and
+/// collects them into a `SignatureBatch`.
+pub fn signed_batch(messages: &[&[u8]], context: &[u8]) -> SignatureBatch {
+    let (signatures, public_keys) = signed_messages(messages, context);
+    let mut batch_sigs = SignatureBatch::new();
+    batch_sigs.set_context(context.to_vec());
+    for ((message, signature), public_key) in messages.iter().zip(signatures).zip(public_keys) {
+        batch_sigs.mut_messages().push(message.to_vec());
+        batch_sigs
+            .mut_signatures()
+            .push(signature.to_bytes().to_vec());
+        batch_sigs
+            .mut_public_keys()
+            .push(public_key.to_bytes().to_vec());
+    }
+    batch_sigs
+}
@@ -0,0 +1,209 @@
+//! An incremental batch verifier which does not depend on the protobuf types.
+
+use super::*;
+use crate::batch::{verify_items, BatchRandomness};
+use rand::{CryptoRng, RngCore};
+
+/// Collects Ed25519ph signatures one at a time and verifies them together.
+/// Signatures and public keys are decoded, and checked against the policy,
+/// as they are added, so that a malformed entry is reported by `add` rather
+/// than by `verify`.
+#[derive(Debug, Clone, Default)]
+pub struct BatchVerifier {
+    policy: VerificationPolicy,
+    items: Vec<BatchItem>,
+}
+
+impl BatchVerifier {
+    pub fn new() -> Self {
+        BatchVerifier::default()
+    }
+
+    /// Decodes and checks the entries added from now on according to `policy`.
+    pub fn with_policy(mut self, policy: VerificationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Adds the signature of `message` under `context`, failing without
+    /// adding it if the signature, public key or context is invalid.
+    pub fn add(
+        &mut self,
+        signature: &[u8],
+        public_key: &[u8],
+        message: &[u8],
+        context: &[u8],
+    ) -> Result<(), CryptoError> {
+        let item = self.decode(signature, public_key, message, context)?;
+        self.items.push(item);
+        Ok(())
+    }
+
+    /// Adds an entry which has already been decoded, in any signature mode.
+    pub fn add_item(&mut self, item: BatchItem) {
+        self.items.push(item);
+    }
+
+    /// Adds every entry of a `SignatureBatch`, leaving it unchanged. If any
+    /// entry is invalid, none is added.
+    pub fn extend_from(&mut self, batch_sigs: &SignatureBatch) -> Result<(), CryptoError> {
+        let messages = batch_sigs.get_messages();
+        let signatures = batch_sigs.get_signatures();
+        let public_keys = batch_sigs.get_public_keys();
+        if signatures.len() != messages.len() || signatures.len() != public_keys.len() {
+            return Err(CryptoError::ArraysNotEqualLength);
+        }
+        let items = (0..signatures.len())
+            .map(|i| {
+                self.decode(
+                    &signatures[i],
+                    &public_keys[i],
+                    &messages[i],
+                    batch_sigs.get_context(),
+                )
+            })
+            .collect::<Result<Vec<BatchItem>, CryptoError>>()?;
+        self.items.extend(items);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Verifies every entry added so far at once, as `batch::verify_batch_items`.
+    pub fn verify<T>(&self, csprng: &mut T) -> Result<(), CryptoError>
+    where
+        T: CryptoRng + RngCore,
+    {
+        self.verify_with_randomness(BatchRandomness::Random, csprng)
+    }
+
+    /// As `verify`, choosing the scalars which weight each entry according to
+    /// `randomness`.
+    pub fn verify_with_randomness<T>(
+        &self,
+        randomness: BatchRandomness,
+        csprng: &mut T,
+    ) -> Result<(), CryptoError>
+    where
+        T: CryptoRng + RngCore,
+    {
+        verify_items(&self.items, constants::BATCH_CHUNK_SIZE, randomness, csprng)
+    }
+
+    fn decode(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        message: &[u8],
+        context: &[u8],
+    ) -> Result<BatchItem, CryptoError> {
+        let signature = self.policy.decode_signature(signature)?;
+        self.policy.check_signature(&signature)?;
+        let public_key = VerifyingKey::from_bytes(public_key)?;
+        self.policy.check_public_key(&public_key)?;
+        BatchItem::new(
+            message,
+            context,
+            SignatureMode::Ed25519ph,
+            signature,
+            public_key,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::EIGHT_TORSION;
+    use rand::rngs::OsRng;
+
+    fn signed(message: &[u8], context: &[u8]) -> ([u8; 64], [u8; 32]) {
+        let key = SigningKey::generate(&mut OsRng {});
+        (
+            key.sign(message, context).unwrap().to_bytes(),
+            key.verifying_key().to_bytes(),
+        )
+    }
+
+    #[test]
+    fn verifier_verifies_added_signatures() {
+        let mut verifier = BatchVerifier::new();
+        for (message, context) in [(&b"first"[..], &b"context"[..]), (b"second", b"")].iter() {
+            let (signature, public_key) = signed(message, context);
+            verifier
+                .add(&signature, &public_key, message, context)
+                .unwrap();
+        }
+        assert_eq!(verifier.len(), 2);
+        assert_eq!(verifier.verify(&mut OsRng {}), Ok(()));
+
+        let (signature, public_key) = signed(b"third", b"context");
+        verifier
+            .add(&signature, &public_key, b"altered", b"context")
+            .unwrap();
+        assert_eq!(
+            verifier.verify(&mut OsRng {}),
+            Err(CryptoError::BatchVerificationFailure)
+        );
+        assert_eq!(
+            BatchVerifier::new().verify(&mut OsRng {}),
+            Err(CryptoError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn verifier_rejects_invalid_entry_on_add() {
+        let mut verifier = BatchVerifier::new().with_policy(VerificationPolicy::strict());
+        let (signature, public_key) = signed(b"message", b"");
+
+        assert_eq!(
+            verifier.add(&signature[1..], &public_key, b"message", b""),
+            Err(CryptoError::InvalidSignature)
+        );
+        assert_eq!(
+            verifier.add(
+                &signature,
+                EIGHT_TORSION[1].compress().as_bytes(),
+                b"message",
+                b""
+            ),
+            Err(CryptoError::InvalidPublicKey)
+        );
+        assert_eq!(
+            verifier.add(&signature, &public_key, b"message", &[0u8; 256]),
+            Err(CryptoError::InvalidContextLength)
+        );
+        assert!(verifier.is_empty());
+    }
+
+    #[test]
+    fn verifier_extends_from_signature_batch() {
+        let context = b"context";
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(context.to_vec());
+        for message in [&b"first"[..], b"second", b"third"].iter() {
+            let (signature, public_key) = signed(message, context);
+            batch_sigs.mut_messages().push(message.to_vec());
+            batch_sigs.mut_signatures().push(signature.to_vec());
+            batch_sigs.mut_public_keys().push(public_key.to_vec());
+        }
+
+        let mut verifier = BatchVerifier::new();
+        verifier.extend_from(&batch_sigs).unwrap();
+        assert_eq!(verifier.len(), 3);
+        assert_eq!(verifier.verify(&mut OsRng {}), Ok(()));
+
+        batch_sigs.mut_signatures()[2].truncate(10);
+        assert_eq!(
+            verifier.extend_from(&batch_sigs),
+            Err(CryptoError::InvalidSignature)
+        );
+        assert_eq!(verifier.len(), 3);
+    }
+}
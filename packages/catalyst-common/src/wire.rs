@@ -0,0 +1,248 @@
+//! Decoding of serialized `SignatureBatch` messages into borrowed views of
+//! the input, so that large batches are verified without copying each
+//! signature, key and message out of the buffer they arrived in.
+
+use super::*;
+
+/// Field numbers of `SignatureBatch` in `Cryptography.proto`.
+const SIGNATURES_FIELD: u64 = 1;
+const PUBLIC_KEYS_FIELD: u64 = 2;
+const MESSAGES_FIELD: u64 = 3;
+const CONTEXT_FIELD: u64 = 4;
+
+const WIRE_TYPE_VARINT: u64 = 0;
+const WIRE_TYPE_FIXED64: u64 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u64 = 2;
+const WIRE_TYPE_FIXED32: u64 = 5;
+
+/// A `SignatureBatch` whose entries borrow from the serialized message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignatureBatchView<'a> {
+    signatures: Vec<&'a [u8]>,
+    public_keys: Vec<&'a [u8]>,
+    messages: Vec<&'a [u8]>,
+    context: &'a [u8],
+}
+
+impl<'a> SignatureBatchView<'a> {
+    /// Decodes a serialized `SignatureBatch`, failing with
+    /// `CryptoError::InvalidBatchMessage` if it is not well formed. Unknown
+    /// fields are skipped, as `SignatureBatch::merge_from_bytes` would.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, CryptoError> {
+        let mut view = SignatureBatchView::default();
+        let mut reader = Reader { bytes };
+        while !reader.bytes.is_empty() {
+            let tag = reader.varint()?;
+            let (field, wire_type) = (tag >> 3, tag & 7);
+            if field == 0 {
+                return Err(CryptoError::InvalidBatchMessage);
+            }
+            let known = match field {
+                SIGNATURES_FIELD => Some(&mut view.signatures),
+                PUBLIC_KEYS_FIELD => Some(&mut view.public_keys),
+                MESSAGES_FIELD => Some(&mut view.messages),
+                CONTEXT_FIELD => None,
+                _ => {
+                    reader.skip(wire_type)?;
+                    continue;
+                }
+            };
+            if wire_type != WIRE_TYPE_LENGTH_DELIMITED {
+                return Err(CryptoError::InvalidBatchMessage);
+            }
+            let value = reader.length_delimited()?;
+            match known {
+                Some(entries) => entries.push(value),
+                // As a singular field, the last occurrence wins.
+                None => view.context = value,
+            }
+        }
+        Ok(view)
+    }
+
+    /// Borrows the entries of a decoded `SignatureBatch`.
+    pub fn from_batch(batch_sigs: &'a SignatureBatch) -> Self {
+        SignatureBatchView {
+            signatures: batch_sigs
+                .get_signatures()
+                .iter()
+                .map(Vec::as_slice)
+                .collect(),
+            public_keys: batch_sigs
+                .get_public_keys()
+                .iter()
+                .map(Vec::as_slice)
+                .collect(),
+            messages: batch_sigs
+                .get_messages()
+                .iter()
+                .map(Vec::as_slice)
+                .collect(),
+            context: batch_sigs.get_context(),
+        }
+    }
+
+    pub(crate) fn from_parts(
+        signatures: Vec<&'a [u8]>,
+        public_keys: Vec<&'a [u8]>,
+        messages: Vec<&'a [u8]>,
+        context: &'a [u8],
+    ) -> Self {
+        SignatureBatchView {
+            signatures,
+            public_keys,
+            messages,
+            context,
+        }
+    }
+
+    /// Copies the entries into a `SignatureBatch`.
+    pub fn to_batch(&self) -> SignatureBatch {
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(self.context.to_vec());
+        for signature in self.signatures.iter() {
+            batch_sigs.mut_signatures().push(signature.to_vec());
+        }
+        for public_key in self.public_keys.iter() {
+            batch_sigs.mut_public_keys().push(public_key.to_vec());
+        }
+        for message in self.messages.iter() {
+            batch_sigs.mut_messages().push(message.to_vec());
+        }
+        batch_sigs
+    }
+
+    pub fn signatures(&self) -> &[&'a [u8]] {
+        &self.signatures
+    }
+
+    pub fn public_keys(&self) -> &[&'a [u8]] {
+        &self.public_keys
+    }
+
+    pub fn messages(&self) -> &[&'a [u8]] {
+        &self.messages
+    }
+
+    pub fn context(&self) -> &'a [u8] {
+        self.context
+    }
+}
+
+/// Reads protobuf wire format values from the front of `bytes`.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn varint(&mut self) -> Result<u64, CryptoError> {
+        let mut value = 0u64;
+        for (i, byte) in self.bytes.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.bytes = &self.bytes[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(CryptoError::InvalidBatchMessage)
+    }
+
+    fn take(&mut self, length: u64) -> Result<&'a [u8], CryptoError> {
+        if length > self.bytes.len() as u64 {
+            return Err(CryptoError::InvalidBatchMessage);
+        }
+        let (value, rest) = self.bytes.split_at(length as usize);
+        self.bytes = rest;
+        Ok(value)
+    }
+
+    fn length_delimited(&mut self) -> Result<&'a [u8], CryptoError> {
+        let length = self.varint()?;
+        self.take(length)
+    }
+
+    /// Skips a value of an unknown field. Groups are deprecated and never
+    /// written for `SignatureBatch`, so they are rejected.
+    fn skip(&mut self, wire_type: u64) -> Result<(), CryptoError> {
+        match wire_type {
+            WIRE_TYPE_VARINT => self.varint().map(|_| ()),
+            WIRE_TYPE_FIXED64 => self.take(8).map(|_| ()),
+            WIRE_TYPE_LENGTH_DELIMITED => self.length_delimited().map(|_| ()),
+            WIRE_TYPE_FIXED32 => self.take(4).map(|_| ()),
+            _ => Err(CryptoError::InvalidBatchMessage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch() -> SignatureBatch {
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context".to_vec());
+        for i in 0..3u8 {
+            batch_sigs.mut_signatures().push(vec![i; 64]);
+            batch_sigs.mut_public_keys().push(vec![i + 1; 32]);
+            batch_sigs
+                .mut_messages()
+                .push(vec![i + 2; 300 * i as usize]);
+        }
+        batch_sigs
+    }
+
+    #[test]
+    fn view_matches_decoded_batch() {
+        let batch_sigs = batch();
+        let bytes = batch_sigs.write_to_bytes().unwrap();
+
+        let view = SignatureBatchView::from_bytes(&bytes).unwrap();
+
+        assert_eq!(view, SignatureBatchView::from_batch(&batch_sigs));
+        assert_eq!(view.messages()[2].len(), 600);
+        assert_eq!(view.context(), b"context");
+    }
+
+    #[test]
+    fn view_skips_unknown_fields() {
+        let mut bytes = batch().write_to_bytes().unwrap();
+        // Field 5 as a varint, field 6 as fixed32 and field 7 as bytes.
+        bytes.extend_from_slice(&[5 << 3, 0x96, 0x01]);
+        bytes.extend_from_slice(&[6 << 3 | 5, 1, 2, 3, 4]);
+        bytes.extend_from_slice(&[7 << 3 | 2, 2, 0xaa, 0xbb]);
+
+        let view = SignatureBatchView::from_bytes(&bytes).unwrap();
+
+        assert_eq!(view, SignatureBatchView::from_batch(&batch()));
+    }
+
+    #[test]
+    fn view_rejects_malformed_messages() {
+        let bytes = batch().write_to_bytes().unwrap();
+        let malformed: Vec<Vec<u8>> = vec![
+            // Truncated in the middle of a value.
+            bytes[..bytes.len() - 1].to_vec(),
+            // A varint which never ends.
+            vec![0xff; 11],
+            // Field 1 as a varint rather than bytes.
+            vec![1 << 3, 1],
+            // Field 0.
+            vec![2, 0],
+            // A start group tag.
+            vec![5 << 3 | 3],
+        ];
+
+        for bytes in malformed.iter() {
+            assert_eq!(
+                SignatureBatchView::from_bytes(bytes),
+                Err(CryptoError::InvalidBatchMessage),
+                "{:?}",
+                bytes
+            );
+        }
+        assert_eq!(
+            SignatureBatchView::from_bytes(&[]),
+            Ok(SignatureBatchView::default())
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! Fixtures shared by the integration tests.
+
+use catalyst_common::{SignatureBatch, SigningKey};
+use rand::rngs::OsRng;
+
+/// The opening lines of Jabberwocky, used as messages throughout the tests.
+pub const MESSAGES: [&[u8]; 3] = [
+    b"'Twas brillig, and the slithy toves",
+    b"Did gyre and gimble in the wabe:",
+    b"All mimsy were the borogoves,",
+];
+
+/// Signs each of `messages` under `context` with a freshly generated key and
+/// collects them into a `SignatureBatch`.
+pub fn signed_batch(messages: &[&[u8]], context: &[u8]) -> SignatureBatch {
+    let mut batch_sigs = SignatureBatch::new();
+    batch_sigs.set_context(context.to_vec());
+    for message in messages {
+        let key = SigningKey::generate(&mut OsRng {});
+        let signature = key.sign(message, context).unwrap();
+        batch_sigs.mut_messages().push(message.to_vec());
+        batch_sigs
+            .mut_signatures()
+            .push(signature.to_bytes().to_vec());
+        batch_sigs
+            .mut_public_keys()
+            .push(key.verifying_key().to_bytes().to_vec());
+    }
+    batch_sigs
+}
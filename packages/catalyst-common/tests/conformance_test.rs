@@ -0,0 +1,443 @@
+use catalyst_common::batch::*;
+use catalyst_common::std_signature::*;
+use catalyst_common::{
+    BatchItem, BatchRandomness, Signature, SignatureBatch, SignatureMode, SigningKey,
+    VerificationPolicy, VerifyingKey,
+};
+use curve25519_dalek::constants::{BASEPOINT_ORDER, ED25519_BASEPOINT_POINT, EIGHT_TORSION};
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::{Digest, Sha512};
+use protobuf::RepeatedField;
+use rand::rngs::OsRng;
+
+/// Edge-case signatures which single and batch verification must agree on,
+/// as described by the verification rule in `catalyst_common::signature`.
+#[cfg(test)]
+mod conformance_tests {
+    use super::*;
+
+    struct Vector {
+        name: &'static str,
+        public_key: [u8; 32],
+        signature: [u8; 64],
+        message: &'static [u8],
+        valid: bool,
+        valid_strict: bool,
+    }
+
+    const MESSAGE: &[u8] = b"Zcash";
+
+    /// The identity encoded with y = p + 1 instead of y = 1.
+    const NON_CANONICAL_IDENTITY: [u8; 32] = [
+        0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ];
+
+    /// The identity encoded with the sign bit of x set although x is zero.
+    const NEGATIVE_ZERO_IDENTITY: [u8; 32] = [
+        0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0x80,
+    ];
+
+    /// Computes `H(dom || R || A || M')` independently of the library.
+    #[allow(non_snake_case)]
+    fn challenge(
+        mode: SignatureMode,
+        context: &[u8],
+        R: &[u8],
+        A: &[u8],
+        message: &[u8],
+    ) -> Scalar {
+        let mut h = Sha512::new();
+        if mode != SignatureMode::Ed25519 {
+            h.input(b"SigEd25519 no Ed25519 collisions");
+            h.input([
+                (mode == SignatureMode::Ed25519ph) as u8,
+                context.len() as u8,
+            ]);
+            h.input(context);
+        }
+        h.input(R);
+        h.input(A);
+        match mode {
+            SignatureMode::Ed25519ph => h.input(Sha512::digest(message)),
+            _ => h.input(message),
+        }
+        Scalar::from_hash(h)
+    }
+
+    #[allow(non_snake_case)]
+    fn encode(R: &[u8; 32], s: &Scalar) -> [u8; 64] {
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(R);
+        signature[32..].copy_from_slice(s.as_bytes());
+        signature
+    }
+
+    /// Signs with secret scalar `a` and nonce `r` for the given encodings of
+    /// `A` and `R`, which need not be `[a]B` and `[r]B`.
+    #[allow(non_snake_case)]
+    fn forge(
+        mode: SignatureMode,
+        context: &[u8],
+        A: &[u8; 32],
+        R: &[u8; 32],
+        a: Scalar,
+        r: Scalar,
+    ) -> [u8; 64] {
+        let k = challenge(mode, context, R, A, MESSAGE);
+        encode(R, &(k * a + r))
+    }
+
+    fn first_invalid_encoding() -> [u8; 32] {
+        (2u8..)
+            .map(|y| {
+                let mut bytes = [0u8; 32];
+                bytes[0] = y;
+                bytes
+            })
+            .find(|bytes| CompressedEdwardsY(*bytes).decompress().is_none())
+            .unwrap()
+    }
+
+    #[allow(non_snake_case)]
+    fn vectors(mode: SignatureMode, context: &[u8]) -> Vec<Vector> {
+        let identity = EdwardsPoint::default().compress().to_bytes();
+        let torsion = EIGHT_TORSION[1];
+        let a = Scalar::from(0x1234_5678u64);
+        let r = Scalar::from(0x8765_4321u64);
+        let aB = (ED25519_BASEPOINT_POINT * a).compress().to_bytes();
+        let rB = (ED25519_BASEPOINT_POINT * r).compress().to_bytes();
+
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let honest = signing_key.sign_with_mode(MESSAGE, context, mode).unwrap();
+        let honest_key = signing_key.verifying_key().to_bytes();
+        let mut unreduced = honest.to_bytes();
+        let s_plus_l = honest.to_bytes()[32..]
+            .iter()
+            .zip(BASEPOINT_ORDER.as_bytes().iter())
+            .scan(0u16, |carry, (s, l)| {
+                let sum = *s as u16 + *l as u16 + *carry;
+                *carry = sum >> 8;
+                Some(sum as u8)
+            })
+            .collect::<Vec<u8>>();
+        unreduced[32..].copy_from_slice(&s_plus_l);
+
+        let A_with_torsion = (ED25519_BASEPOINT_POINT * a + torsion)
+            .compress()
+            .to_bytes();
+        let R_with_torsion = (ED25519_BASEPOINT_POINT * r + torsion)
+            .compress()
+            .to_bytes();
+        let small_order = torsion.compress().to_bytes();
+
+        vec![
+            Vector {
+                name: "honest signature",
+                public_key: honest_key,
+                signature: honest.to_bytes(),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: true,
+            },
+            Vector {
+                name: "honest signature over a different message",
+                public_key: honest_key,
+                signature: honest.to_bytes(),
+                message: b"Zcash!",
+                valid: false,
+                valid_strict: false,
+            },
+            Vector {
+                name: "s not reduced modulo the group order",
+                public_key: honest_key,
+                signature: unreduced,
+                message: MESSAGE,
+                valid: false,
+                valid_strict: false,
+            },
+            Vector {
+                name: "identity public key and R with s = 0",
+                public_key: identity,
+                signature: encode(&identity, &Scalar::zero()),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "public key of order 8 and identity R with s = 0",
+                public_key: small_order,
+                signature: encode(&identity, &Scalar::zero()),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "public key with a torsion component",
+                public_key: A_with_torsion,
+                signature: forge(mode, context, &A_with_torsion, &rB, a, r),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "R with a torsion component",
+                public_key: aB,
+                signature: forge(mode, context, &aB, &R_with_torsion, a, r),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "non-canonical encoding of the public key",
+                public_key: NON_CANONICAL_IDENTITY,
+                signature: encode(&identity, &Scalar::zero()),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "non-canonical encoding of R",
+                public_key: identity,
+                signature: encode(&NON_CANONICAL_IDENTITY, &Scalar::zero()),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "negative zero encoding of the public key",
+                public_key: NEGATIVE_ZERO_IDENTITY,
+                signature: encode(&identity, &Scalar::zero()),
+                message: MESSAGE,
+                valid: true,
+                valid_strict: false,
+            },
+            Vector {
+                name: "R not on the curve",
+                public_key: aB,
+                signature: forge(mode, context, &aB, &first_invalid_encoding(), a, r),
+                message: MESSAGE,
+                valid: false,
+                valid_strict: false,
+            },
+            Vector {
+                name: "public key not on the curve",
+                public_key: first_invalid_encoding(),
+                signature: encode(&identity, &Scalar::zero()),
+                message: MESSAGE,
+                valid: false,
+                valid_strict: false,
+            },
+        ]
+    }
+
+    fn batch_item(vector: &Vector, mode: SignatureMode, context: &[u8]) -> Option<BatchItem> {
+        let signature = Signature::from_bytes(&vector.signature).ok()?;
+        let public_key = VerifyingKey::from_bytes(&vector.public_key).ok()?;
+        BatchItem::new(vector.message, context, mode, signature, public_key).ok()
+    }
+
+    const MODES: [(SignatureMode, &[u8]); 3] = [
+        (SignatureMode::Ed25519, b""),
+        (SignatureMode::Ed25519ctx, b"context"),
+        (SignatureMode::Ed25519ph, b"context"),
+    ];
+
+    #[test]
+    fn single_verification_follows_the_rule() {
+        for (mode, context) in MODES.iter() {
+            for vector in vectors(*mode, context) {
+                let result = try_verify_with_mode(
+                    &vector.signature,
+                    &vector.public_key,
+                    vector.message,
+                    context,
+                    *mode,
+                );
+                assert_eq!(result.is_ok(), vector.valid, "{:?}: {}", mode, vector.name);
+            }
+        }
+    }
+
+    #[test]
+    fn batch_of_one_agrees_with_single_verification() {
+        for (mode, context) in MODES.iter() {
+            for vector in vectors(*mode, context) {
+                // The random scalars differ between runs, so repeat to catch
+                // any dependence on them.
+                for _ in 0..8 {
+                    let valid = batch_item(&vector, *mode, context)
+                        .map(|item| verify_batch_items(&[item], &mut OsRng {}).is_ok())
+                        .unwrap_or(false);
+                    assert_eq!(valid, vector.valid, "{:?}: {}", mode, vector.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn batch_of_every_valid_vector_passes() {
+        for (mode, context) in MODES.iter() {
+            let items: Vec<BatchItem> = vectors(*mode, context)
+                .iter()
+                .filter(|vector| vector.valid)
+                .map(|vector| batch_item(vector, *mode, context).unwrap())
+                .collect();
+
+            assert_eq!(
+                verify_batch_items(&items, &mut OsRng {}),
+                Ok(()),
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn transcript_randomness_rejects_the_same_signatures_as_random() {
+        for (mode, context) in MODES.iter() {
+            let vectors = vectors(*mode, context);
+            let valid_items: Vec<BatchItem> = vectors
+                .iter()
+                .filter(|vector| vector.valid)
+                .map(|vector| batch_item(vector, *mode, context).unwrap())
+                .collect();
+
+            for vector in vectors.iter() {
+                let item = match batch_item(vector, *mode, context) {
+                    Some(item) => item,
+                    None => continue,
+                };
+                // Alone, and hidden among every valid vector.
+                let mut mixed = valid_items.clone();
+                mixed.insert(mixed.len() / 2, item.clone());
+                for items in [vec![item], mixed].iter() {
+                    let random = verify_batch_items(items, &mut OsRng {}).is_ok();
+                    for extra_entropy in [&b""[..], b"extra entropy"].iter() {
+                        let transcript = verify_batch_items_with_randomness(
+                            items,
+                            BatchRandomness::Transcript(extra_entropy),
+                            &mut OsRng {},
+                        )
+                        .is_ok();
+                        assert_eq!(transcript, random, "{:?}: {}", mode, vector.name);
+                        assert_eq!(transcript, vector.valid, "{:?}: {}", mode, vector.name);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn per_entry_batch_results_agree_with_single_verification() {
+        for (mode, context) in MODES.iter() {
+            let vectors = vectors(*mode, context);
+            let (items, expected): (Vec<BatchItem>, Vec<bool>) = vectors
+                .iter()
+                .filter_map(|vector| {
+                    batch_item(vector, *mode, context).map(|item| (item, vector.valid))
+                })
+                .unzip();
+
+            assert_eq!(
+                verify_batch_items_each(&items, &mut OsRng {}),
+                Ok(expected),
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn signature_batch_agrees_with_single_verification() {
+        let context = b"context";
+        let vectors = vectors(SignatureMode::Ed25519ph, context);
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(context.to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(
+            vectors.iter().map(|v| v.message.to_vec()).collect(),
+        ));
+        batch_sigs.set_signatures(RepeatedField::from_vec(
+            vectors.iter().map(|v| v.signature.to_vec()).collect(),
+        ));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(
+            vectors.iter().map(|v| v.public_key.to_vec()).collect(),
+        ));
+
+        let expected: Vec<bool> = vectors.iter().map(|v| v.valid).collect();
+        assert_eq!(verify_batch_each(&batch_sigs, &mut OsRng {}), Ok(expected));
+    }
+
+    fn verify_single(
+        vector: &Vector,
+        mode: SignatureMode,
+        context: &[u8],
+        policy: &VerificationPolicy,
+    ) -> bool {
+        try_verify_with_policy(
+            &vector.signature,
+            &vector.public_key,
+            vector.message,
+            context,
+            mode,
+            policy,
+        )
+        .is_ok()
+    }
+
+    fn verify_batch_of_one(vector: &Vector, context: &[u8], policy: &VerificationPolicy) -> bool {
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(context.to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(vec![vector.message.to_vec()]));
+        batch_sigs.set_signatures(RepeatedField::from_vec(vec![vector.signature.to_vec()]));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(vec![vector.public_key.to_vec()]));
+        try_verify_batch_with_policy(&mut batch_sigs, policy, &mut OsRng {}).is_ok()
+    }
+
+    #[test]
+    fn strict_policy_accepts_only_honest_signatures_in_both_paths() {
+        let policy = VerificationPolicy::strict();
+        for (mode, context) in MODES.iter() {
+            for vector in vectors(*mode, context) {
+                assert_eq!(
+                    verify_single(&vector, *mode, context, &policy),
+                    vector.valid_strict,
+                    "{:?}: {}",
+                    mode,
+                    vector.name
+                );
+            }
+        }
+        for vector in vectors(SignatureMode::Ed25519ph, b"context") {
+            assert_eq!(
+                verify_batch_of_one(&vector, b"context", &policy),
+                vector.valid_strict,
+                "{}",
+                vector.name
+            );
+        }
+    }
+
+    #[test]
+    fn permissive_policy_also_accepts_unreduced_scalars_in_both_paths() {
+        let policy = VerificationPolicy::permissive();
+        for vector in vectors(SignatureMode::Ed25519ph, b"context") {
+            let expected = vector.valid || vector.name == "s not reduced modulo the group order";
+            assert_eq!(
+                verify_single(&vector, SignatureMode::Ed25519ph, b"context", &policy),
+                expected,
+                "{}",
+                vector.name
+            );
+            assert_eq!(
+                verify_batch_of_one(&vector, b"context", &policy),
+                expected,
+                "{}",
+                vector.name
+            );
+        }
+    }
+}
@@ -1,7 +1,10 @@
+mod common;
+
 use catalyst_common::batch::*;
 use catalyst_common::constants;
 use catalyst_common::keys::*;
 use catalyst_common::std_signature::*;
+use catalyst_common::BatchRandomness;
 pub use catalyst_protocol_sdk_rust::prelude::*;
 pub use catalyst_protocol_sdk_rust::Cryptography::{ErrorCode, SignatureBatch};
 use protobuf::RepeatedField;
@@ -283,4 +286,147 @@ mod integration_tests {
 
         assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
     }
+
+    #[test]
+    fn batch_verify_returns_error_for_malformed_signature() {
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.mut_signatures()[1].truncate(10);
+
+        let result = verify_batch(&mut batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, ErrorCode::INVALID_SIGNATURE.value());
+    }
+
+    #[test]
+    fn batch_verify_returns_error_for_public_key_not_on_curve() {
+        let mut invalid_public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        publickey_from_private(
+            &mut invalid_public_key,
+            &[0u8; constants::PRIVATE_KEY_LENGTH],
+        );
+        invalid_public_key[constants::PUBLIC_KEY_LENGTH - 1] =
+            invalid_public_key[constants::PUBLIC_KEY_LENGTH - 1].wrapping_add(1u8);
+
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.mut_public_keys()[2] = invalid_public_key.to_vec();
+
+        let result = verify_batch(&mut batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, ErrorCode::INVALID_PUBLIC_KEY.value());
+    }
+
+    #[test]
+    fn batch_verify_returns_error_for_unequal_array_lengths() {
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.mut_messages().pop();
+
+        let result = verify_batch(&mut batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, ErrorCode::ARRAYS_NOT_EQUAL_LENGTH.value());
+    }
+
+    #[test]
+    fn batch_verify_with_transcript_randomness_is_repeatable() {
+        let batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        for _ in 0..2 {
+            let result = verify_batch_with_randomness(
+                &mut batch_sigs.clone(),
+                BatchRandomness::Transcript(b"block 1"),
+                &mut OsRng {},
+            );
+            assert_eq!(result, ErrorCode::NO_ERROR.value());
+        }
+
+        let mut batch_sigs = batch_sigs;
+        batch_sigs.mut_messages()[0].push(0);
+        let result = verify_batch_with_randomness(
+            &mut batch_sigs,
+            BatchRandomness::Transcript(b""),
+            &mut OsRng {},
+        );
+
+        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+    }
+
+    #[test]
+    fn batch_verify_returns_error_for_context_exceeding_maximum() {
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.set_context(vec![0u8; constants::CONTEXT_MAX_LENGTH + 1]);
+
+        let result = verify_batch(&mut batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, ErrorCode::INVALID_CONTEXT_LENGTH.value());
+    }
+
+    #[test]
+    fn batch_verify_each_accepts_every_correct_signature() {
+        let batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+
+        let result = verify_batch_each(&batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, Ok(vec![true, true, true]));
+    }
+
+    #[test]
+    fn batch_verify_each_identifies_incorrect_message() {
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.mut_messages()[1] = b"The jaws that bite, the claws that catch!".to_vec();
+
+        let result = verify_batch_each(&batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, Ok(vec![true, false, true]));
+    }
+
+    #[test]
+    fn batch_verify_each_marks_undecodable_entries_invalid() {
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.mut_signatures()[0].truncate(10);
+        batch_sigs.mut_public_keys()[2].truncate(10);
+
+        let result = verify_batch_each(&batch_sigs, &mut OsRng {});
+
+        assert_eq!(result, Ok(vec![false, true, false]));
+    }
+
+    #[test]
+    fn batch_verify_each_returns_error_for_unequal_array_lengths() {
+        let mut batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        batch_sigs.mut_public_keys().pop();
+
+        let result = verify_batch_each(&batch_sigs, &mut OsRng {});
+
+        assert_eq!(
+            result,
+            Err(catalyst_common::CryptoError::ArraysNotEqualLength)
+        );
+    }
+
+    #[test]
+    fn batch_items_decoded_from_signature_batch_verify() {
+        let batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+
+        let items = catalyst_common::BatchItem::from_signature_batch(&batch_sigs).unwrap();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(verify_batch_items(&items, &mut OsRng {}), Ok(()));
+    }
+
+    #[test]
+    fn batch_verify_with_cache_uses_validator_tables() {
+        let batch_sigs = common::signed_batch(&common::MESSAGES, b"context");
+        let mut cache = catalyst_common::VerificationKeyCache::new(16);
+        // Only some of the signers are validators.
+        cache
+            .set_validators(&batch_sigs.get_public_keys()[..2])
+            .unwrap();
+
+        let result = verify_batch_with_cache(&mut batch_sigs.clone(), &mut cache, &mut OsRng {});
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        assert_eq!(cache.len(), 1);
+
+        let mut altered = batch_sigs;
+        altered.mut_messages()[0] = b"The jaws that bite, the claws that catch!".to_vec();
+        let result = verify_batch_with_cache(&mut altered, &mut cache, &mut OsRng {});
+        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+    }
 }
@@ -2,25 +2,31 @@
 extern crate criterion;
 
 use criterion::black_box;
-use criterion::Criterion;
+use criterion::{BenchmarkId, Criterion, Throughput};
 
+use catalyst_common::batch;
+use catalyst_common::{Message, SignatureBatch, SigningKey};
 use catalyst_ffi::constants;
 use catalyst_ffi::ffi;
+use rand::rngs::OsRng;
 
 pub fn sign_benchmark(c: &mut Criterion) {
     let mut sig = [0u8; constants::SIGNATURE_LENGTH];
     let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
-    ffi::generate_private_key(&mut private_key);
+    unsafe { ffi::generate_private_key(private_key.as_mut_ptr(), private_key.len()) };
     let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
 
     let message = String::from("Message 1 2 3");
     let context = String::from("Context 1 2 3");
     c.bench_function("sign ed25519ph", |b| {
-        b.iter(|| {
+        b.iter(|| unsafe {
             ffi::std_sign(
-                black_box(&mut sig),
-                black_box(&mut public_key),
-                black_box(&private_key),
+                black_box(sig.as_mut_ptr()),
+                black_box(sig.len()),
+                black_box(public_key.as_mut_ptr()),
+                black_box(public_key.len()),
+                black_box(private_key.as_ptr()),
+                black_box(private_key.len()),
                 black_box(message.as_ptr()),
                 black_box(message.len()),
                 black_box(context.as_ptr()),
@@ -33,28 +39,42 @@ pub fn sign_benchmark(c: &mut Criterion) {
 pub fn verify_benchmark(c: &mut Criterion) {
     let mut sig = [0u8; constants::SIGNATURE_LENGTH];
     let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
-    ffi::generate_private_key(&mut private_key);
+    unsafe { ffi::generate_private_key(private_key.as_mut_ptr(), private_key.len()) };
     let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
 
     let message = String::from("Message 1 2 3");
     let context = String::from("Context 1 2 3");
 
-    ffi::std_sign(
-        &mut sig,
-        &mut public_key,
-        &private_key,
-        message.as_ptr(),
-        message.len(),
-        context.as_ptr(),
-        context.len(),
-    );
-    ffi::publickey_from_private(&mut public_key, &private_key);
+    unsafe {
+        ffi::std_sign(
+            sig.as_mut_ptr(),
+            sig.len(),
+            public_key.as_mut_ptr(),
+            public_key.len(),
+            private_key.as_ptr(),
+            private_key.len(),
+            message.as_ptr(),
+            message.len(),
+            context.as_ptr(),
+            context.len(),
+        )
+    };
+    unsafe {
+        ffi::publickey_from_private(
+            public_key.as_mut_ptr(),
+            public_key.len(),
+            private_key.as_ptr(),
+            private_key.len(),
+        )
+    };
 
     c.bench_function("verify ed25519ph", |b| {
-        b.iter(|| {
+        b.iter(|| unsafe {
             ffi::std_verify(
-                black_box(&mut sig),
-                black_box(&public_key),
+                black_box(sig.as_ptr()),
+                black_box(sig.len()),
+                black_box(public_key.as_ptr()),
+                black_box(public_key.len()),
                 black_box(message.as_ptr()),
                 black_box(message.len()),
                 black_box(context.as_ptr()),
@@ -64,5 +84,41 @@ pub fn verify_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benchmarks, sign_benchmark, verify_benchmark);
+pub fn batch_verify_benchmark(c: &mut Criterion) {
+    let context = b"Context 1 2 3";
+    let mut group = c.benchmark_group("batch verify ed25519ph");
+    group.sample_size(10);
+    for size in [256, 4096].iter() {
+        let keys: Vec<SigningKey> = (0..*size)
+            .map(|_| SigningKey::generate(&mut OsRng {}))
+            .collect();
+        let keys: Vec<&SigningKey> = keys.iter().collect();
+        let messages: Vec<Vec<u8>> = (0..*size).map(|i| vec![i as u8; 1024]).collect();
+        let bytes = batch::sign_signature_batch(&keys, &messages, context)
+            .unwrap()
+            .write_to_bytes()
+            .unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        // Decoding into a `SignatureBatch` as `verify_batch` did before it read
+        // the buffer in place.
+        group.bench_with_input(BenchmarkId::new("copied", size), &bytes, |b, bytes| {
+            b.iter(|| {
+                let mut batch_sigs = SignatureBatch::new();
+                batch_sigs.merge_from_bytes(black_box(bytes)).unwrap();
+                batch::try_verify_batch(&mut batch_sigs, &mut OsRng {})
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("borrowed", size), &bytes, |b, bytes| {
+            b.iter(|| unsafe { ffi::verify_batch(black_box(bytes.as_ptr()), bytes.len()) })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benchmarks,
+    sign_benchmark,
+    verify_benchmark,
+    batch_verify_benchmark
+);
 criterion_main!(benchmarks);
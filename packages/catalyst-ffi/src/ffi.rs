@@ -1,72 +1,1765 @@
 //! The foreign function interface which exposes this library to non-Rust
 //! languages. Error codes returned are as defined in protocol protobuffs https://github.com/catalyst-network/protocol-protobuffs/blob/develop/src/Cryptography.proto
+//!
+//! Every buffer is passed as a pointer along with its length in bytes. A
+//! buffer of fixed size whose length is wrong is reported with the error code
+//! for that kind of buffer, as is a null pointer to a non-empty buffer. Null
+//! pointers or bad lengths for messages, which have no error code of their
+//! own, are reported as `ERROR_CODE_UNKNOWN`.
+//!
+//! No function unwinds into the caller: a panic is caught at the boundary and
+//! reported as `ERROR_CODE_UNKNOWN`.
 
 use super::*;
-use libc::c_int;
-use rand::rngs::OsRng;
+use libc::{c_int, c_uint, c_void};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
 use std::slice;
+use zeroize::Zeroize;
+
+/// Runs `f` and converts its result into an error code, catching any panic so
+/// that it cannot unwind across the C ABI.
+fn guard<F>(f: F) -> c_int
+where
+    F: FnOnce() -> Result<(), CryptoError>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => error::to_error_code(result),
+        Err(_) => ErrorCode::ERROR_CODE_UNKNOWN.value(),
+    }
+}
+
+/// Borrows `length` bytes from `ptr`, failing with `error` if `ptr` is null
+/// while `length` is not zero. A null pointer with zero length borrows an
+/// empty slice.
+///
+/// # Safety
+///
+/// If `ptr` is not null it must be valid for reads of `length` bytes for the
+/// lifetime `'a`.
+unsafe fn borrow_slice<'a>(
+    ptr: *const u8,
+    length: usize,
+    error: CryptoError,
+) -> Result<&'a [u8], CryptoError> {
+    if ptr.is_null() {
+        return if length == 0 { Ok(&[]) } else { Err(error) };
+    }
+    Ok(slice::from_raw_parts(ptr, length))
+}
+
+/// Borrows exactly `expected_length` bytes from `ptr`, failing with `error`
+/// if `ptr` is null or `length` differs from `expected_length`.
+///
+/// # Safety
+///
+/// If `ptr` is not null it must be valid for reads of `length` bytes for the
+/// lifetime `'a`.
+unsafe fn borrow_exact<'a>(
+    ptr: *const u8,
+    length: usize,
+    expected_length: usize,
+    error: CryptoError,
+) -> Result<&'a [u8], CryptoError> {
+    if ptr.is_null() || length != expected_length {
+        return Err(error);
+    }
+    Ok(slice::from_raw_parts(ptr, length))
+}
+
+/// Mutably borrows exactly `expected_length` bytes from `ptr`, failing with
+/// `error` if `ptr` is null or `length` differs from `expected_length`.
+///
+/// # Safety
+///
+/// If `ptr` is not null it must be valid for writes of `length` bytes for
+/// the lifetime `'a`, and must not be aliased by any other buffer argument.
+unsafe fn borrow_exact_mut<'a>(
+    ptr: *mut u8,
+    length: usize,
+    expected_length: usize,
+    error: CryptoError,
+) -> Result<&'a mut [u8], CryptoError> {
+    if ptr.is_null() || length != expected_length {
+        return Err(error);
+    }
+    Ok(slice::from_raw_parts_mut(ptr, length))
+}
+
+/// Splits `buffer` at `offset_count` offsets read from `offsets`, so that entry
+/// `i` spans `offsets[i]..offsets[i + 1]`. Every byte of `buffer` must belong
+/// to an entry, so this fails with `error` if `offsets` is null, does not
+/// start at 0, decreases, or does not end at the end of `buffer`.
+///
+/// # Safety
+///
+/// If `offsets` is not null it must be valid for reads of `offset_count` offsets.
+unsafe fn split_at_offsets(
+    buffer: &[u8],
+    offsets: *const u64,
+    offset_count: usize,
+    error: CryptoError,
+) -> Result<Vec<&[u8]>, CryptoError> {
+    if offsets.is_null() || offset_count == 0 {
+        return Err(error);
+    }
+    let offsets = slice::from_raw_parts(offsets, offset_count);
+    if offsets[0] != 0 || offsets[offset_count - 1] != buffer.len() as u64 {
+        return Err(error);
+    }
+    offsets
+        .windows(2)
+        .map(|bounds| {
+            if bounds[0] > bounds[1] {
+                return Err(error);
+            }
+            // Both bounds are at most `buffer.len()`, so they fit in a usize.
+            Ok(&buffer[bounds[0] as usize..bounds[1] as usize])
+        })
+        .collect()
+}
+
+/// Copies a SHA-512 prehash from `ptr`, failing with `ERROR_CODE_UNKNOWN`, the
+/// code for a message which cannot be read, if `ptr` is null or `length` is not
+/// `constants::PREHASH_LENGTH`.
+///
+/// # Safety
+///
+/// If `ptr` is not null it must be valid for reads of `length` bytes.
+unsafe fn read_prehash(
+    ptr: *const u8,
+    length: usize,
+) -> Result<[u8; constants::PREHASH_LENGTH], CryptoError> {
+    let mut prehash = [0u8; constants::PREHASH_LENGTH];
+    prehash.copy_from_slice(borrow_exact(
+        ptr,
+        length,
+        constants::PREHASH_LENGTH,
+        CryptoError::Unknown,
+    )?);
+    Ok(prehash)
+}
 
 /// Verifies that an ed25519 signature corresponds to the provided public key, message, and context. Returns 0 if no error encountered, otherwise returns an error code. Sets value of is_verified based of verification outcome.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads of the length passed with it.
 #[no_mangle]
-pub extern "C" fn std_verify(
-    signature: &[u8; constants::SIGNATURE_LENGTH],
-    publickey: &[u8; constants::PUBLIC_KEY_LENGTH],
+pub unsafe extern "C" fn std_verify(
+    signature: *const u8,
+    signature_length: usize,
+    publickey: *const u8,
+    publickey_length: usize,
     message: *const u8,
     message_length: usize,
     context: *const u8,
     context_length: usize,
 ) -> c_int {
-    let message = unsafe { slice::from_raw_parts(message, message_length) };
-    let context = unsafe { slice::from_raw_parts(context, context_length) };
-    std_signature::verify(signature, publickey, message, context)
+    std_verify_with_mode(
+        signature,
+        signature_length,
+        publickey,
+        publickey_length,
+        message,
+        message_length,
+        context,
+        context_length,
+        SignatureMode::Ed25519ph as c_int,
+    )
 }
 
 /// Creates a signature from private key and message.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffers, of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_sign(
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    std_sign_with_mode(
+        out_signature,
+        out_signature_length,
+        out_public_key,
+        out_public_key_length,
+        private_key,
+        private_key_length,
+        message,
+        message_length,
+        context,
+        context_length,
+        SignatureMode::Ed25519ph as c_int,
+    )
+}
+
+/// Verifies a signature made with the RFC 8032 variant given by `mode`: 0 for
+/// pure Ed25519, 1 for Ed25519ctx and 2 for Ed25519ph.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_verify_with_mode(
+    signature: *const u8,
+    signature_length: usize,
+    publickey: *const u8,
+    publickey_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+    mode: c_int,
+) -> c_int {
+    std_verify_with_policy(
+        signature,
+        signature_length,
+        publickey,
+        publickey_length,
+        message,
+        message_length,
+        context,
+        context_length,
+        mode,
+        VerificationPolicy::default().to_flags(),
+    )
+}
+
+/// Verifies a signature made with the RFC 8032 variant given by `mode`, under
+/// the verification policy given by `policy_flags`, a bitmask of the
+/// `policy::REJECT_*` flags. Unknown flags are reported as `ERROR_CODE_UNKNOWN`.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_verify_with_policy(
+    signature: *const u8,
+    signature_length: usize,
+    publickey: *const u8,
+    publickey_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+    mode: c_int,
+    policy_flags: c_uint,
+) -> c_int {
+    guard(|| {
+        let mode = SignatureMode::from_i32(mode).ok_or(CryptoError::Unknown)?;
+        let policy = VerificationPolicy::from_flags(policy_flags).ok_or(CryptoError::Unknown)?;
+        let signature = borrow_exact(
+            signature,
+            signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let publickey = borrow_exact(
+            publickey,
+            publickey_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let message = borrow_slice(message, message_length, CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        std_signature::try_verify_with_policy(signature, publickey, message, context, mode, &policy)
+    })
+}
+
+/// Creates a signature with the RFC 8032 variant given by `mode`, first
+/// checking the public key of `private_key` against the verification policy
+/// given by `policy_flags`, as `std_verify_with_policy` reads them. A key the
+/// policy rejects, such as a weak key, is reported as `INVALID_PUBLIC_KEY` and
+/// nothing is signed.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffers, of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_sign_with_policy(
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+    mode: c_int,
+    policy_flags: c_uint,
+) -> c_int {
+    guard(|| {
+        let mode = SignatureMode::from_i32(mode).ok_or(CryptoError::Unknown)?;
+        let policy = VerificationPolicy::from_flags(policy_flags).ok_or(CryptoError::Unknown)?;
+        let out_signature = borrow_exact_mut(
+            out_signature,
+            out_signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let out_public_key = borrow_exact_mut(
+            out_public_key,
+            out_public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let private_key = borrow_exact(
+            private_key,
+            private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let message = borrow_slice(message, message_length, CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+
+        let signing_key = SigningKey::from_bytes(private_key)?;
+        let signature = signing_key.sign_with_policy(message, context, mode, &policy)?;
+        out_signature.copy_from_slice(&signature.to_bytes());
+        out_public_key.copy_from_slice(signing_key.verifying_key().as_bytes());
+        Ok(())
+    })
+}
+
+/// Creates a signature with the RFC 8032 variant given by `mode`: 0 for pure
+/// Ed25519, 1 for Ed25519ctx and 2 for Ed25519ph.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffers, of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_sign_with_mode(
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+    mode: c_int,
+) -> c_int {
+    std_sign_with_options(
+        out_signature,
+        out_signature_length,
+        out_public_key,
+        out_public_key_length,
+        private_key,
+        private_key_length,
+        message,
+        message_length,
+        context,
+        context_length,
+        mode,
+        SigningOptions::deterministic().to_flags(),
+        std::ptr::null_mut(),
+    )
+}
+
+/// Creates a signature with the RFC 8032 variant given by `mode`, applying
+/// the countermeasures given by `options_flags`, a bitmask of the
+/// `signing::HEDGED_NONCE` and `signing::VERIFY_AFTER_SIGN` flags. Unknown
+/// flags are reported as `ERROR_CODE_UNKNOWN`, and a signature which fails
+/// the self check as `SIGNATURE_VERIFICATION_FAILURE`. A hedged nonce draws
+/// its randomness from `source`, or from the operating system if `source` is
+/// null; deterministic signing draws nothing.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffers, of the length passed with it.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
 #[no_mangle]
-pub extern "C" fn std_sign(
-    out_signature: &mut [u8; constants::SIGNATURE_LENGTH],
-    out_public_key: &mut [u8; constants::PUBLIC_KEY_LENGTH],
-    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+pub unsafe extern "C" fn std_sign_with_options(
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
     message: *const u8,
     message_length: usize,
     context: *const u8,
     context_length: usize,
+    mode: c_int,
+    options_flags: c_uint,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let mode = SignatureMode::from_i32(mode).ok_or(CryptoError::Unknown)?;
+        let options = SigningOptions::from_flags(options_flags).ok_or(CryptoError::Unknown)?;
+        let out_signature = borrow_exact_mut(
+            out_signature,
+            out_signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let out_public_key = borrow_exact_mut(
+            out_public_key,
+            out_public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let private_key = borrow_exact(
+            private_key,
+            private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let message = borrow_slice(message, message_length, CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+
+        let signing_key = SigningKey::from_bytes(private_key)?;
+        let signature = sign_drawing_from(source, &options, |csprng| {
+            signing_key.sign_with_options(message, context, mode, &options, csprng)
+        })?;
+        out_signature.copy_from_slice(&signature.to_bytes());
+        out_public_key.copy_from_slice(signing_key.verifying_key().as_bytes());
+        Ok(())
+    })
+}
+
+/// Creates an Ed25519ph signature from the SHA-512 digest of the message, of
+/// `get_prehash_length()` bytes, making the signature `std_sign` would make
+/// over the message itself. A null digest or one of any other length is
+/// reported as `ERROR_CODE_UNKNOWN`, as a null message is.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffers, of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_sign_prehashed(
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
+    prehash: *const u8,
+    prehash_length: usize,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    guard(|| {
+        let out_signature = borrow_exact_mut(
+            out_signature,
+            out_signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let out_public_key = borrow_exact_mut(
+            out_public_key,
+            out_public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let private_key = borrow_exact(
+            private_key,
+            private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let prehash = read_prehash(prehash, prehash_length)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+
+        let signing_key = SigningKey::from_bytes(private_key)?;
+        let signature = signing_key.sign_prehashed(&prehash, context)?;
+        out_signature.copy_from_slice(&signature.to_bytes());
+        out_public_key.copy_from_slice(signing_key.verifying_key().as_bytes());
+        Ok(())
+    })
+}
+
+/// Verifies an Ed25519ph signature given the SHA-512 digest of the message,
+/// of `get_prehash_length()` bytes, as `std_verify` would verify it over the
+/// message itself. A null digest or one of any other length is reported as
+/// `ERROR_CODE_UNKNOWN`, as a null message is.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn std_verify_prehashed(
+    signature: *const u8,
+    signature_length: usize,
+    publickey: *const u8,
+    publickey_length: usize,
+    prehash: *const u8,
+    prehash_length: usize,
+    context: *const u8,
+    context_length: usize,
 ) -> c_int {
-    let message = unsafe { slice::from_raw_parts(message, message_length) };
-    let context = unsafe { slice::from_raw_parts(context, context_length) };
-    std_signature::sign(out_signature, out_public_key, private_key, message, context)
+    guard(|| {
+        let signature = borrow_exact(
+            signature,
+            signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let publickey = borrow_exact(
+            publickey,
+            publickey_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let prehash = read_prehash(prehash, prehash_length)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        std_signature::try_verify_prehashed_with_policy(
+            signature,
+            publickey,
+            &prehash,
+            context,
+            &VerificationPolicy::default(),
+        )
+    })
 }
 
 /// Calculates corresponding public key, given a private key.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffer, of the length passed with it.
 #[no_mangle]
-pub extern "C" fn publickey_from_private(
-    out_publickey: &mut [u8; constants::PUBLIC_KEY_LENGTH],
-    private_key: &[u8; constants::PRIVATE_KEY_LENGTH],
+pub unsafe extern "C" fn publickey_from_private(
+    out_publickey: *mut u8,
+    out_publickey_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
 ) -> c_int {
-    keys::publickey_from_private(out_publickey, private_key)
+    guard(|| {
+        let out_publickey = borrow_exact_mut(
+            out_publickey,
+            out_publickey_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let private_key = borrow_exact(
+            private_key,
+            private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let signing_key = SigningKey::from_bytes(private_key)?;
+        out_publickey.copy_from_slice(signing_key.verifying_key().as_bytes());
+        Ok(())
+    })
 }
 
 /// Checks public key is a valid point on the curve.
+///
+/// # Safety
+///
+/// `public_key` must either be null or be valid for reads of `public_key_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn validate_public_key(
+    public_key: *const u8,
+    public_key_length: usize,
+) -> c_int {
+    guard(|| {
+        let public_key = borrow_exact(
+            public_key,
+            public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        VerifyingKey::from_bytes(public_key).map(|_| ())
+    })
+}
+
+/// Checks public key is a valid point on the curve which passes the public
+/// key checks of the verification policy given by `policy_flags`, a bitmask of
+/// the `policy::REJECT_*` flags. Unknown flags are reported as `ERROR_CODE_UNKNOWN`.
+///
+/// # Safety
+///
+/// `public_key` must either be null or be valid for reads of `public_key_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn validate_public_key_with_policy(
+    public_key: *const u8,
+    public_key_length: usize,
+    policy_flags: c_uint,
+) -> c_int {
+    guard(|| {
+        let policy = VerificationPolicy::from_flags(policy_flags).ok_or(CryptoError::Unknown)?;
+        let public_key = borrow_exact(
+            public_key,
+            public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        policy.check_public_key(&VerifyingKey::from_bytes(public_key)?)
+    })
+}
+
+/// Writes a report on the point encoded by the public key into `out_report`,
+/// as a bitmask of the `keys::PUBLIC_KEY_*` flags: 1 if the encoding is
+/// canonical, 2 if the point has small order, 4 if it is torsion free and 8 if
+/// it is the identity. Fails with `INVALID_PUBLIC_KEY` if the key is not a
+/// point on the curve.
+///
+/// # Safety
+///
+/// `public_key` must either be null or be valid for reads of `public_key_length`
+/// bytes, and `out_report` must either be null or be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn inspect_public_key(
+    public_key: *const u8,
+    public_key_length: usize,
+    out_report: *mut c_uint,
+) -> c_int {
+    guard(|| {
+        let public_key = borrow_exact(
+            public_key,
+            public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let out_report = out_report.as_mut().ok_or(CryptoError::Unknown)?;
+        *out_report = keys::inspect_public_key(public_key)?.to_flags();
+        Ok(())
+    })
+}
+
+/// Verifies a protobuf encoded `SignatureBatch`, reading its entries in place
+/// rather than copying them. A batch which cannot be decoded is reported as
+/// `INVALID_BATCH_MESSAGE`.
+///
+/// # Safety
+///
+/// `bytes` must either be null or be valid for reads of `bytes_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch(bytes: *const u8, bytes_length: usize) -> c_int {
+    verify_batch_with_source(bytes, bytes_length, std::ptr::null_mut())
+}
+
+/// Verifies a serialized `SignatureBatch` as `verify_batch` does, drawing the
+/// scalars which weight each signature from `source`, or from the operating
+/// system if `source` is null.
+///
+/// # Safety
+///
+/// `bytes` must either be null or be valid for reads of `bytes_length` bytes.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch_with_source(
+    bytes: *const u8,
+    bytes_length: usize,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let bytes = borrow_slice(bytes, bytes_length, CryptoError::InvalidBatchMessage)?;
+        let view = SignatureBatchView::from_bytes(bytes)?;
+        with_source(source, |csprng| {
+            batch::try_verify_batch_view(
+                &view,
+                &VerificationPolicy::default(),
+                BatchRandomness::Random,
+                csprng,
+            )
+        })
+    })
+}
+
+/// Verifies a serialized `SignatureBatch` under the verification policy given
+/// by `policy_flags`, a bitmask of the `policy::REJECT_*` flags. Unknown flags
+/// are reported as `ERROR_CODE_UNKNOWN`. The scalars which weight each
+/// signature are drawn from `source`, or from the operating system if it is
+/// null.
+///
+/// # Safety
+///
+/// `bytes` must either be null or be valid for reads of `bytes_length` bytes.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch_with_policy(
+    bytes: *const u8,
+    bytes_length: usize,
+    policy_flags: c_uint,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let policy = VerificationPolicy::from_flags(policy_flags).ok_or(CryptoError::Unknown)?;
+        let bytes = borrow_slice(bytes, bytes_length, CryptoError::InvalidBatchMessage)?;
+        let view = SignatureBatchView::from_bytes(bytes)?;
+        with_source(source, |csprng| {
+            batch::try_verify_batch_view(&view, &policy, BatchRandomness::Random, csprng)
+        })
+    })
+}
+
+/// Verifies a serialized `SignatureBatch`, choosing the scalars which weight
+/// each signature as given by `randomness`: 0 draws them from `source`, or
+/// from the operating system's generator if `source` is null, and 1 derives
+/// them from a transcript of the batch mixed with `extra_entropy`, so that the
+/// same batch always gets the same result. `extra_entropy` may be empty, and is
+/// ignored when `randomness` is 0. Other values of `randomness` are reported as
+/// `ERROR_CODE_UNKNOWN`.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads of the length passed with it.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch_with_randomness(
+    bytes: *const u8,
+    bytes_length: usize,
+    randomness: c_int,
+    extra_entropy: *const u8,
+    extra_entropy_length: usize,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let extra_entropy =
+            borrow_slice(extra_entropy, extra_entropy_length, CryptoError::Unknown)?;
+        let randomness =
+            BatchRandomness::from_i32(randomness, extra_entropy).ok_or(CryptoError::Unknown)?;
+        let bytes = borrow_slice(bytes, bytes_length, CryptoError::InvalidBatchMessage)?;
+        let view = SignatureBatchView::from_bytes(bytes)?;
+        with_source(source, |csprng| {
+            batch::try_verify_batch_view(&view, &VerificationPolicy::default(), randomness, csprng)
+        })
+    })
+}
+
+/// Verifies a serialized `SignatureBatch` and writes the validity of each entry
+/// into `out_results`, 1 for a valid signature and 0 otherwise, in the order of
+/// the batch's signatures. `out_results_length` must equal the number of
+/// signatures in the batch. Returns `BATCH_VERIFICATION_FAILURE` once the
+/// results are written if any entry is invalid. The scalars which weight each
+/// signature are drawn from `source`, or from the operating system if it is
+/// null.
+///
+/// # Safety
+///
+/// `bytes` must either be null or be valid for reads of `bytes_length` bytes, and
+/// `out_results` must either be null or be valid for writes of `out_results_length` bytes.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch_each(
+    bytes: *const u8,
+    bytes_length: usize,
+    out_results: *mut u8,
+    out_results_length: usize,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let bytes = borrow_slice(bytes, bytes_length, CryptoError::InvalidBatchMessage)?;
+        let view = SignatureBatchView::from_bytes(bytes)?;
+        let out_results = borrow_exact_mut(
+            out_results,
+            out_results_length,
+            view.signatures().len(),
+            CryptoError::ArraysNotEqualLength,
+        )?;
+        let results = with_source(source, |csprng| {
+            batch::verify_batch_view_each(&view, csprng)
+        })?;
+        write_results(out_results, &results)
+    })
+}
+
+/// Verifies `entry_count` Ed25519ph signatures given as flat arrays, without
+/// a serialized `SignatureBatch`. `signatures` holds `entry_count` signatures
+/// of `get_signature_length()` bytes each, and `public_keys` as many public
+/// keys of `get_public_key_length()` bytes each. The messages are concatenated
+/// in `messages`, message `i` spanning `message_offsets[i]` to
+/// `message_offsets[i + 1]`, so `message_offsets` holds `entry_count + 1`
+/// offsets, the first 0 and the last `messages_length`. If `context_offsets`
+/// is null, `contexts` is one context shared by every entry; otherwise the
+/// contexts are concatenated in `contexts` and `context_offsets` holds
+/// `entry_count + 1` offsets in the same way.
+///
+/// If `out_results` is null only the batch as a whole is verified. Otherwise
+/// `out_results_length` must equal `entry_count`, and the validity of each
+/// entry is written into `out_results` as `verify_batch_each` does, with
+/// entries that cannot be decoded reported as invalid. The scalars which weight
+/// each signature are drawn from `source`, or from the operating system if it
+/// is null.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffer, of the length passed with it. `message_offsets` and any
+/// non-null `context_offsets` must be valid for reads of `entry_count + 1` offsets.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch_arrays(
+    signatures: *const u8,
+    signatures_length: usize,
+    public_keys: *const u8,
+    public_keys_length: usize,
+    messages: *const u8,
+    messages_length: usize,
+    message_offsets: *const u64,
+    contexts: *const u8,
+    contexts_length: usize,
+    context_offsets: *const u64,
+    entry_count: usize,
+    out_results: *mut u8,
+    out_results_length: usize,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let offset_count = entry_count.checked_add(1).ok_or(CryptoError::Unknown)?;
+        let signatures = borrow_exact(
+            signatures,
+            signatures_length,
+            entry_count
+                .checked_mul(constants::SIGNATURE_LENGTH)
+                .ok_or(CryptoError::InvalidSignature)?,
+            CryptoError::InvalidSignature,
+        )?;
+        let public_keys = borrow_exact(
+            public_keys,
+            public_keys_length,
+            entry_count
+                .checked_mul(constants::PUBLIC_KEY_LENGTH)
+                .ok_or(CryptoError::InvalidPublicKey)?,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let messages = borrow_slice(messages, messages_length, CryptoError::Unknown)?;
+        let messages = split_at_offsets(
+            messages,
+            message_offsets,
+            offset_count,
+            CryptoError::Unknown,
+        )?;
+        let contexts = borrow_slice(contexts, contexts_length, CryptoError::InvalidContextLength)?;
+        let contexts = if context_offsets.is_null() {
+            vec![contexts]
+        } else {
+            split_at_offsets(
+                contexts,
+                context_offsets,
+                offset_count,
+                CryptoError::InvalidContextLength,
+            )?
+        };
+        let signatures: Vec<&[u8]> = signatures.chunks(constants::SIGNATURE_LENGTH).collect();
+        let public_keys: Vec<&[u8]> = public_keys.chunks(constants::PUBLIC_KEY_LENGTH).collect();
+
+        if out_results.is_null() {
+            return with_source(source, |csprng| {
+                batch::try_verify_batch_arrays(
+                    &signatures,
+                    &public_keys,
+                    &messages,
+                    &contexts,
+                    csprng,
+                )
+            });
+        }
+        let out_results = borrow_exact_mut(
+            out_results,
+            out_results_length,
+            entry_count,
+            CryptoError::ArraysNotEqualLength,
+        )?;
+        let results = with_source(source, |csprng| {
+            batch::verify_batch_arrays_each(&signatures, &public_keys, &messages, &contexts, csprng)
+        })?;
+        write_results(out_results, &results)
+    })
+}
+
+/// As `verify_batch_arrays`, given the SHA-512 digest of each message rather
+/// than the message itself. `prehashes` holds `entry_count` digests of
+/// `get_prehash_length()` bytes each, so no message crosses the boundary. If
+/// `prehashes` is null or of any other length `ERROR_CODE_UNKNOWN` is
+/// returned, as for messages which cannot be split.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffer, of the length passed with it. Any non-null `context_offsets`
+/// must be valid for reads of `entry_count + 1` offsets.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn verify_batch_prehashed(
+    signatures: *const u8,
+    signatures_length: usize,
+    public_keys: *const u8,
+    public_keys_length: usize,
+    prehashes: *const u8,
+    prehashes_length: usize,
+    contexts: *const u8,
+    contexts_length: usize,
+    context_offsets: *const u64,
+    entry_count: usize,
+    out_results: *mut u8,
+    out_results_length: usize,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let signatures = borrow_exact(
+            signatures,
+            signatures_length,
+            entry_count
+                .checked_mul(constants::SIGNATURE_LENGTH)
+                .ok_or(CryptoError::InvalidSignature)?,
+            CryptoError::InvalidSignature,
+        )?;
+        let public_keys = borrow_exact(
+            public_keys,
+            public_keys_length,
+            entry_count
+                .checked_mul(constants::PUBLIC_KEY_LENGTH)
+                .ok_or(CryptoError::InvalidPublicKey)?,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let prehashes = borrow_exact(
+            prehashes,
+            prehashes_length,
+            entry_count
+                .checked_mul(constants::PREHASH_LENGTH)
+                .ok_or(CryptoError::Unknown)?,
+            CryptoError::Unknown,
+        )?;
+        let contexts = borrow_slice(contexts, contexts_length, CryptoError::InvalidContextLength)?;
+        let contexts = if context_offsets.is_null() {
+            vec![contexts]
+        } else {
+            split_at_offsets(
+                contexts,
+                context_offsets,
+                entry_count.checked_add(1).ok_or(CryptoError::Unknown)?,
+                CryptoError::InvalidContextLength,
+            )?
+        };
+        let signatures: Vec<&[u8]> = signatures.chunks(constants::SIGNATURE_LENGTH).collect();
+        let public_keys: Vec<&[u8]> = public_keys.chunks(constants::PUBLIC_KEY_LENGTH).collect();
+        let prehashes: Vec<[u8; constants::PREHASH_LENGTH]> = prehashes
+            .chunks(constants::PREHASH_LENGTH)
+            .map(|chunk| {
+                let mut prehash = [0u8; constants::PREHASH_LENGTH];
+                prehash.copy_from_slice(chunk);
+                prehash
+            })
+            .collect();
+
+        if out_results.is_null() {
+            return with_source(source, |csprng| {
+                batch::try_verify_batch_prehashed(
+                    &signatures,
+                    &public_keys,
+                    &prehashes,
+                    &contexts,
+                    csprng,
+                )
+            });
+        }
+        let out_results = borrow_exact_mut(
+            out_results,
+            out_results_length,
+            entry_count,
+            CryptoError::ArraysNotEqualLength,
+        )?;
+        let results = with_source(source, |csprng| {
+            batch::verify_batch_prehashed_each(
+                &signatures,
+                &public_keys,
+                &prehashes,
+                &contexts,
+                csprng,
+            )
+        })?;
+        write_results(out_results, &results)
+    })
+}
+
+/// Writes the validity of each entry into `out_results`, failing if any is
+/// invalid.
+fn write_results(out_results: &mut [u8], results: &[bool]) -> Result<(), CryptoError> {
+    for (out, valid) in out_results.iter_mut().zip(results.iter()) {
+        *out = *valid as u8;
+    }
+    if results.iter().all(|valid| *valid) {
+        Ok(())
+    } else {
+        Err(CryptoError::BatchVerificationFailure)
+    }
+}
+
+/// Signs `message_count` messages with Ed25519ph under a shared context and
+/// writes the serialized `SignatureBatch` into `out_batch`. The messages are
+/// concatenated in `messages` and split by `message_count + 1` offsets in
+/// `message_offsets`, as for `verify_batch_arrays`.
+/// `private_keys` holds either one private key, which signs every message, or
+/// one per message; each distinct key is expanded only once.
+///
+/// The length of the serialized batch is written to `out_batch_written`. If
+/// `out_batch` is null nothing is signed and `NO_ERROR` is returned, so that
+/// the caller can size the buffer before signing. If `out_batch` is too small
+/// to hold the batch nothing is written to it and `INVALID_BATCH_MESSAGE` is
+/// returned.
+///
+/// # Safety
+///
+/// Each pointer must either be null or be valid for reads, or writes for the
+/// `out_` buffers, of the length passed with it. `message_offsets` must be valid
+/// for reads of `message_count + 1` offsets, and `out_batch_written` for a write
+/// of one.
+#[no_mangle]
+pub unsafe extern "C" fn sign_batch(
+    out_batch: *mut u8,
+    out_batch_length: usize,
+    out_batch_written: *mut usize,
+    private_keys: *const u8,
+    private_keys_length: usize,
+    messages: *const u8,
+    messages_length: usize,
+    message_offsets: *const u64,
+    message_count: usize,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    guard(|| {
+        if out_batch_written.is_null() {
+            return Err(CryptoError::Unknown);
+        }
+        let messages = borrow_slice(messages, messages_length, CryptoError::Unknown)?;
+        let messages = split_at_offsets(
+            messages,
+            message_offsets,
+            message_count.checked_add(1).ok_or(CryptoError::Unknown)?,
+            CryptoError::Unknown,
+        )?;
+
+        let key_count = if private_keys_length == constants::PRIVATE_KEY_LENGTH {
+            1
+        } else {
+            message_count
+        };
+        let private_keys = borrow_exact(
+            private_keys,
+            private_keys_length,
+            key_count
+                .checked_mul(constants::PRIVATE_KEY_LENGTH)
+                .ok_or(CryptoError::InvalidPrivateKey)?,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let mut expanded: Vec<SigningKey> = Vec::new();
+        let mut positions: HashMap<&[u8], usize> = HashMap::new();
+        let mut key_indices = Vec::with_capacity(key_count);
+        for private_key in private_keys.chunks(constants::PRIVATE_KEY_LENGTH) {
+            let index = match positions.get(private_key) {
+                Some(&index) => index,
+                None => {
+                    expanded.push(SigningKey::from_bytes(private_key)?);
+                    positions.insert(private_key, expanded.len() - 1);
+                    expanded.len() - 1
+                }
+            };
+            key_indices.push(index);
+        }
+        let keys: Vec<&SigningKey> = key_indices.iter().map(|&i| &expanded[i]).collect();
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        SignatureMode::Ed25519ph.check_context(context)?;
+
+        if out_batch.is_null() {
+            *out_batch_written = batch::signature_batch_length(&messages, context);
+            return Ok(());
+        }
+        let batch = batch::sign_signature_batch(&keys, &messages, context)?
+            .write_to_bytes()
+            .map_err(|_| CryptoError::Unknown)?;
+        *out_batch_written = batch.len();
+        if batch.len() > out_batch_length {
+            return Err(CryptoError::InvalidBatchMessage);
+        }
+        let out_batch = borrow_exact_mut(
+            out_batch,
+            out_batch_length,
+            out_batch_length,
+            CryptoError::InvalidBatchMessage,
+        )?;
+        out_batch[..batch.len()].copy_from_slice(&batch);
+        Ok(())
+    })
+}
+
+/// Creates an empty `BatchVerifier`, to be released with `batch_verifier_free`.
+#[no_mangle]
+pub extern "C" fn batch_verifier_new() -> *mut BatchVerifier {
+    Box::into_raw(Box::new(BatchVerifier::new()))
+}
+
+/// Adds an Ed25519ph signature to the verifier, failing without adding it if
+/// the signature, public key or context is invalid. A null `verifier` is
+/// reported as `ERROR_CODE_UNKNOWN`.
+///
+/// # Safety
+///
+/// `verifier` must either be null or have been returned by `batch_verifier_new`
+/// and not yet freed. Each other pointer must either be null or be valid for
+/// reads of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn batch_verifier_add(
+    verifier: *mut BatchVerifier,
+    signature: *const u8,
+    signature_length: usize,
+    publickey: *const u8,
+    publickey_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    guard(|| {
+        let verifier = verifier.as_mut().ok_or(CryptoError::Unknown)?;
+        let signature = borrow_slice(signature, signature_length, CryptoError::InvalidSignature)?;
+        let publickey = borrow_slice(publickey, publickey_length, CryptoError::InvalidPublicKey)?;
+        let message = borrow_slice(message, message_length, CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        verifier.add(signature, publickey, message, context)
+    })
+}
+
+/// Verifies every signature added to the verifier at once. The verifier keeps
+/// its signatures, so that more can be added and verified again. The scalars
+/// which weight each signature are drawn from `source`, or from the operating
+/// system if it is null.
+///
+/// # Safety
+///
+/// `verifier` must either be null or have been returned by `batch_verifier_new`
+/// and not yet freed.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn batch_verifier_verify(
+    verifier: *const BatchVerifier,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let verifier = verifier.as_ref().ok_or(CryptoError::Unknown)?;
+        with_source(source, |csprng| verifier.verify(csprng))
+    })
+}
+
+/// Releases a verifier. Passing null does nothing.
+///
+/// # Safety
+///
+/// `verifier` must either be null or have been returned by `batch_verifier_new`
+/// and not yet freed. It must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn batch_verifier_free(verifier: *mut BatchVerifier) {
+    if !verifier.is_null() {
+        drop(Box::from_raw(verifier));
+    }
+}
+
+/// Starts an Ed25519ph signature over a message given in pieces with
+/// `sign_stream_update`, writing a `Signer` to `out_signer` to be released
+/// with `sign_stream_free`. Nothing is written if the context is too long.
+///
+/// # Safety
+///
+/// `out_signer` must either be null or be valid for writes of one pointer.
+/// `context` must either be null or be valid for reads of `context_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sign_stream_init(
+    out_signer: *mut *mut Signer,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    guard(|| {
+        let out_signer = out_signer.as_mut().ok_or(CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        *out_signer = Box::into_raw(Box::new(Signer::new(context)?));
+        Ok(())
+    })
+}
+
+/// Appends a piece of the message to be signed.
+///
+/// # Safety
+///
+/// `signer` must either be null or have been written by `sign_stream_init`
+/// and not yet freed. `data` must either be null or be valid for reads of
+/// `data_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sign_stream_update(
+    signer: *mut Signer,
+    data: *const u8,
+    data_length: usize,
+) -> c_int {
+    guard(|| {
+        let signer = signer.as_mut().ok_or(CryptoError::Unknown)?;
+        signer.update(borrow_slice(data, data_length, CryptoError::Unknown)?);
+        Ok(())
+    })
+}
+
+/// Signs the message given so far, as `std_sign` would sign it whole. The
+/// signer is left unchanged and must still be released with `sign_stream_free`.
+///
+/// # Safety
+///
+/// `signer` must either be null or have been written by `sign_stream_init`
+/// and not yet freed. Each other pointer must either be null or be valid for
+/// reads, or writes for the `out_` buffers, of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn sign_stream_final(
+    signer: *const Signer,
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+    private_key: *const u8,
+    private_key_length: usize,
+) -> c_int {
+    guard(|| {
+        let signer = signer.as_ref().ok_or(CryptoError::Unknown)?;
+        let out_signature = borrow_exact_mut(
+            out_signature,
+            out_signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let out_public_key = borrow_exact_mut(
+            out_public_key,
+            out_public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        let private_key = borrow_exact(
+            private_key,
+            private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+
+        let signing_key = SigningKey::from_bytes(private_key)?;
+        let signature = signer.clone().finalize_sign(&signing_key)?;
+        out_signature.copy_from_slice(&signature.to_bytes());
+        out_public_key.copy_from_slice(signing_key.verifying_key().as_bytes());
+        Ok(())
+    })
+}
+
+/// Releases a signer. Passing null does nothing.
+///
+/// # Safety
+///
+/// `signer` must either be null or have been written by `sign_stream_init`
+/// and not yet freed. It must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn sign_stream_free(signer: *mut Signer) {
+    if !signer.is_null() {
+        drop(Box::from_raw(signer));
+    }
+}
+
+/// Starts the verification of an Ed25519ph signature over a message given in
+/// pieces with `verify_stream_update`, writing a `Verifier` to `out_verifier`
+/// to be released with `verify_stream_free`. Nothing is written if the
+/// context is too long.
+///
+/// # Safety
+///
+/// `out_verifier` must either be null or be valid for writes of one pointer.
+/// `context` must either be null or be valid for reads of `context_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn verify_stream_init(
+    out_verifier: *mut *mut Verifier,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    guard(|| {
+        let out_verifier = out_verifier.as_mut().ok_or(CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        *out_verifier = Box::into_raw(Box::new(Verifier::new(context)?));
+        Ok(())
+    })
+}
+
+/// Appends a piece of the message whose signature is verified.
+///
+/// # Safety
+///
+/// `verifier` must either be null or have been written by `verify_stream_init`
+/// and not yet freed. `data` must either be null or be valid for reads of
+/// `data_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn verify_stream_update(
+    verifier: *mut Verifier,
+    data: *const u8,
+    data_length: usize,
+) -> c_int {
+    guard(|| {
+        let verifier = verifier.as_mut().ok_or(CryptoError::Unknown)?;
+        verifier.update(borrow_slice(data, data_length, CryptoError::Unknown)?);
+        Ok(())
+    })
+}
+
+/// Verifies the signature over the message given so far, as `std_verify`
+/// would verify it whole. The verifier is left unchanged and must still be
+/// released with `verify_stream_free`.
+///
+/// # Safety
+///
+/// `verifier` must either be null or have been written by `verify_stream_init`
+/// and not yet freed. Each other pointer must either be null or be valid for
+/// reads of the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn verify_stream_final(
+    verifier: *const Verifier,
+    signature: *const u8,
+    signature_length: usize,
+    publickey: *const u8,
+    publickey_length: usize,
+) -> c_int {
+    guard(|| {
+        let verifier = verifier.as_ref().ok_or(CryptoError::Unknown)?;
+        let signature = borrow_exact(
+            signature,
+            signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let publickey = borrow_exact(
+            publickey,
+            publickey_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        verifier.clone().finalize_verify_bytes(signature, publickey)
+    })
+}
+
+/// Releases a verifier. Passing null does nothing.
+///
+/// # Safety
+///
+/// `verifier` must either be null or have been written by `verify_stream_init`
+/// and not yet freed. It must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn verify_stream_free(verifier: *mut Verifier) {
+    if !verifier.is_null() {
+        drop(Box::from_raw(verifier));
+    }
+}
+
+/// Expands a private key into a keypair held by the library, writing it to
+/// `out_keypair` to be released with `keypair_free`. The private key buffer
+/// can be wiped by the caller as soon as this returns. With the `mlock`
+/// feature, failing to lock the keypair's memory is reported as
+/// `ERROR_CODE_UNKNOWN`.
+///
+/// # Safety
+///
+/// `out_keypair` must either be null or be valid for writes of one pointer.
+/// `private_key` must either be null or be valid for reads of `private_key_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn keypair_from_private_key(
+    out_keypair: *mut *mut LockedBox<SigningKey>,
+    private_key: *const u8,
+    private_key_length: usize,
+) -> c_int {
+    guard(|| {
+        let out_keypair = out_keypair.as_mut().ok_or(CryptoError::Unknown)?;
+        let private_key = borrow_exact(
+            private_key,
+            private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        // Built directly in the call, so that `LockedBox::new` wipes the only
+        // copy of the expanded key outside the keypair's memory.
+        let keypair = LockedBox::new(SigningKey::from_bytes(private_key)?)
+            .map_err(|_| CryptoError::Unknown)?;
+        *out_keypair = Box::into_raw(Box::new(keypair));
+        Ok(())
+    })
+}
+
+/// Generates a keypair held by the library from `source`, or from the
+/// operating system if `source` is null, writing it to `out_keypair` to be
+/// released with `keypair_free`. The private key never leaves the library
+/// unless `keypair_export_private_key` is called.
+///
+/// # Safety
+///
+/// `out_keypair` must either be null or be valid for writes of one pointer.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn keypair_generate(
+    out_keypair: *mut *mut LockedBox<SigningKey>,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let out_keypair = out_keypair.as_mut().ok_or(CryptoError::Unknown)?;
+        let keypair = with_source(source, |csprng| {
+            LockedBox::new(SigningKey::try_generate(csprng)?).map_err(|_| CryptoError::Unknown)
+        })?;
+        *out_keypair = Box::into_raw(Box::new(keypair));
+        Ok(())
+    })
+}
+
+/// Writes the public key of a keypair.
+///
+/// # Safety
+///
+/// `keypair` must either be null or have been written by `keypair_generate`
+/// or `keypair_from_private_key` and not yet freed. `out_public_key` must
+/// either be null or be valid for writes of `out_public_key_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn keypair_public_key(
+    keypair: *const LockedBox<SigningKey>,
+    out_public_key: *mut u8,
+    out_public_key_length: usize,
+) -> c_int {
+    guard(|| {
+        let keypair = keypair.as_ref().ok_or(CryptoError::Unknown)?;
+        let out_public_key = borrow_exact_mut(
+            out_public_key,
+            out_public_key_length,
+            constants::PUBLIC_KEY_LENGTH,
+            CryptoError::InvalidPublicKey,
+        )?;
+        out_public_key.copy_from_slice(keypair.verifying_key().as_bytes());
+        Ok(())
+    })
+}
+
+/// Signs the message under the given context with Ed25519ph, making the
+/// signature `std_sign` would make with the keypair's private key.
+///
+/// # Safety
+///
+/// `keypair` must either be null or have been written by `keypair_generate`
+/// or `keypair_from_private_key` and not yet freed. Each other pointer must
+/// either be null or be valid for reads, or writes for the `out_` buffer, of
+/// the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn keypair_sign(
+    keypair: *const LockedBox<SigningKey>,
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+) -> c_int {
+    keypair_sign_with_options(
+        keypair,
+        out_signature,
+        out_signature_length,
+        message,
+        message_length,
+        context,
+        context_length,
+        SigningOptions::deterministic().to_flags(),
+        std::ptr::null_mut(),
+    )
+}
+
+/// Signs the message under the given context with Ed25519ph, applying the
+/// countermeasures given by `options_flags` and drawing from `source` as
+/// `std_sign_with_options` does.
+///
+/// # Safety
+///
+/// `keypair` must either be null or have been written by `keypair_generate`
+/// or `keypair_from_private_key` and not yet freed. Each other pointer must
+/// either be null or be valid for reads, or writes for the `out_` buffer, of
+/// the length passed with it.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn keypair_sign_with_options(
+    keypair: *const LockedBox<SigningKey>,
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+    options_flags: c_uint,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let keypair = keypair.as_ref().ok_or(CryptoError::Unknown)?;
+        let options = SigningOptions::from_flags(options_flags).ok_or(CryptoError::Unknown)?;
+        let out_signature = borrow_exact_mut(
+            out_signature,
+            out_signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let message = borrow_slice(message, message_length, CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        let signature = sign_drawing_from(source, &options, |csprng| {
+            keypair.sign_with_options(message, context, SignatureMode::Ed25519ph, &options, csprng)
+        })?;
+        out_signature.copy_from_slice(&signature.to_bytes());
+        Ok(())
+    })
+}
+
+/// Signs the message under the given context with Ed25519ph, first checking
+/// the keypair's public key against the verification policy given by
+/// `policy_flags` as `std_sign_with_policy` does.
+///
+/// # Safety
+///
+/// `keypair` must either be null or have been written by `keypair_generate`
+/// or `keypair_from_private_key` and not yet freed. Each other pointer must
+/// either be null or be valid for reads, or writes for the `out_` buffer, of
+/// the length passed with it.
+#[no_mangle]
+pub unsafe extern "C" fn keypair_sign_with_policy(
+    keypair: *const LockedBox<SigningKey>,
+    out_signature: *mut u8,
+    out_signature_length: usize,
+    message: *const u8,
+    message_length: usize,
+    context: *const u8,
+    context_length: usize,
+    policy_flags: c_uint,
+) -> c_int {
+    guard(|| {
+        let keypair = keypair.as_ref().ok_or(CryptoError::Unknown)?;
+        let policy = VerificationPolicy::from_flags(policy_flags).ok_or(CryptoError::Unknown)?;
+        let out_signature = borrow_exact_mut(
+            out_signature,
+            out_signature_length,
+            constants::SIGNATURE_LENGTH,
+            CryptoError::InvalidSignature,
+        )?;
+        let message = borrow_slice(message, message_length, CryptoError::Unknown)?;
+        let context = borrow_slice(context, context_length, CryptoError::InvalidContextLength)?;
+        let signature =
+            keypair.sign_with_policy(message, context, SignatureMode::Ed25519ph, &policy)?;
+        out_signature.copy_from_slice(&signature.to_bytes());
+        Ok(())
+    })
+}
+
+/// Writes the private key of a keypair, for backing it up or moving it to
+/// another store. This is the only way the private key leaves the library.
+///
+/// # Safety
+///
+/// `keypair` must either be null or have been written by `keypair_generate`
+/// or `keypair_from_private_key` and not yet freed. `out_private_key` must
+/// either be null or be valid for writes of `out_private_key_length` bytes.
 #[no_mangle]
-pub extern "C" fn validate_public_key(
-    public_key: &mut [u8; constants::PUBLIC_KEY_LENGTH]
+pub unsafe extern "C" fn keypair_export_private_key(
+    keypair: *const LockedBox<SigningKey>,
+    out_private_key: *mut u8,
+    out_private_key_length: usize,
 ) -> c_int {
-    keys::validate_public_key(public_key)
+    guard(|| {
+        let keypair = keypair.as_ref().ok_or(CryptoError::Unknown)?;
+        let out_private_key = borrow_exact_mut(
+            out_private_key,
+            out_private_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let mut private_key = keypair.to_bytes();
+        out_private_key.copy_from_slice(&private_key);
+        private_key.zeroize();
+        Ok(())
+    })
 }
 
+/// Wipes and releases a keypair. Passing null does nothing.
+///
+/// # Safety
+///
+/// `keypair` must either be null or have been written by `keypair_generate`
+/// or `keypair_from_private_key` and not yet freed. It must not be used after
+/// this call.
 #[no_mangle]
-#[allow(unused_must_use)]
-pub extern "C" fn verify_batch(bytes: *const u8, bytes_length: usize,) -> c_int {
-    let bytes = unsafe { slice::from_raw_parts(bytes, bytes_length) };
-    let mut batch_sigs = SignatureBatch::new();
-    batch_sigs.merge_from_bytes(bytes);
-    batch::verify_batch(&mut batch_sigs, &mut OsRng {})
+pub unsafe extern "C" fn keypair_free(keypair: *mut LockedBox<SigningKey>) {
+    if !keypair.is_null() {
+        drop(Box::from_raw(keypair));
+    }
 }
 
 /// Randomly generated private key.
+///
+/// # Safety
+///
+/// `out_key` must either be null or be valid for writes of `out_key_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn generate_private_key(out_key: *mut u8, out_key_length: usize) -> c_int {
+    generate_private_key_with_source(out_key, out_key_length, std::ptr::null_mut())
+}
+
+/// Generates a private key from `source`, or from the operating system if
+/// `source` is null.
+///
+/// # Safety
+///
+/// `out_key` must either be null or be valid for writes of `out_key_length`
+/// bytes.
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn generate_private_key_with_source(
+    out_key: *mut u8,
+    out_key_length: usize,
+    source: *mut RandomSource,
+) -> c_int {
+    guard(|| {
+        let out_key = borrow_exact_mut(
+            out_key,
+            out_key_length,
+            constants::PRIVATE_KEY_LENGTH,
+            CryptoError::InvalidPrivateKey,
+        )?;
+        let mut private_key = with_source(source, keys::try_generate_private_key)?;
+        out_key.copy_from_slice(&private_key);
+        private_key.zeroize();
+        Ok(())
+    })
+}
+
+/// Fills `length` bytes at `out` with random bytes, returning 0 on success
+/// and anything else on failure. `user_data` is passed through unchanged.
+pub type RandomBytesCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, out: *mut u8, length: usize) -> c_int>;
+
+/// Creates a source which draws its bytes from `callback`, such as a host's
+/// DRBG, writing it to `out_source` to be released with `random_source_free`.
+/// The caller vouches that the bytes are unpredictable. A failing callback
+/// is reported as `ERROR_CODE_UNKNOWN` by the function drawing from it.
+///
+/// # Safety
+///
+/// `out_source` must either be null or be valid for writes of one pointer.
+/// `callback` must be safe to call with `user_data` for as long as the source
+/// is used, and must only write to the buffer it is given.
+#[no_mangle]
+pub unsafe extern "C" fn random_source_from_callback(
+    out_source: *mut *mut RandomSource,
+    callback: RandomBytesCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(|| {
+        let out_source = out_source.as_mut().ok_or(CryptoError::Unknown)?;
+        let callback = callback.ok_or(CryptoError::Unknown)?;
+        let source = RandomSource::from_fn(move |dest: &mut [u8]| {
+            match callback(user_data, dest.as_mut_ptr(), dest.len()) {
+                0 => Ok(()),
+                _ => Err(rand::Error::new("The random bytes callback failed.")),
+            }
+        });
+        *out_source = Box::into_raw(Box::new(source));
+        Ok(())
+    })
+}
+
+/// **Insecure: never use this in production.** Creates a source whose bytes
+/// all follow from the `rng::SEED_LENGTH` bytes of `seed`, so that keys and
+/// test networks can be reproduced, writing it to `out_source` to be released
+/// with `random_source_free`. Anyone who learns the seed learns every key
+/// generated from it.
+///
+/// # Safety
+///
+/// `out_source` must either be null or be valid for writes of one pointer.
+/// `seed` must either be null or be valid for reads of `seed_length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn random_source_insecure_from_seed(
+    out_source: *mut *mut RandomSource,
+    seed: *const u8,
+    seed_length: usize,
+) -> c_int {
+    guard(|| {
+        let out_source = out_source.as_mut().ok_or(CryptoError::Unknown)?;
+        let seed = borrow_exact(seed, seed_length, rng::SEED_LENGTH, CryptoError::Unknown)?;
+        let mut fixed_seed = [0u8; rng::SEED_LENGTH];
+        fixed_seed.copy_from_slice(seed);
+        *out_source = Box::into_raw(Box::new(RandomSource::insecure_from_seed(fixed_seed)));
+        fixed_seed.zeroize();
+        Ok(())
+    })
+}
+
+/// Releases a random source. Passing null does nothing.
+///
+/// # Safety
+///
+/// `source` must either be null or have been written by one of the
+/// `random_source_*` functions and not yet freed. It must not be used after
+/// this call.
 #[no_mangle]
-pub extern "C" fn generate_private_key(out_key: &mut [u8; constants::PRIVATE_KEY_LENGTH]) -> c_int {
-    keys::generate_private_key(out_key, &mut OsRng {})
+pub unsafe extern "C" fn random_source_free(source: *mut RandomSource) {
+    if !source.is_null() {
+        drop(Box::from_raw(source));
+    }
+}
+
+/// Runs `f` with the source behind `source`, or with the operating system's
+/// generator if it is null.
+///
+/// # Safety
+///
+/// `source` must either be null or point to a live `RandomSource` which is
+/// not otherwise borrowed.
+unsafe fn with_source<F, R>(source: *mut RandomSource, f: F) -> Result<R, CryptoError>
+where
+    F: FnOnce(&mut RandomSource) -> Result<R, CryptoError>,
+{
+    match source.as_mut() {
+        Some(source) => f(source),
+        None => f(&mut RandomSource::os()),
+    }
+}
+
+/// Runs `sign` with the source behind `source` if `options` hedge the nonce,
+/// and otherwise with the operating system's generator, which deterministic
+/// signing never draws from, so that it cannot fail for want of randomness.
+///
+/// # Safety
+///
+/// As for `with_source`.
+unsafe fn sign_drawing_from<F>(
+    source: *mut RandomSource,
+    options: &SigningOptions,
+    sign: F,
+) -> Result<Signature, CryptoError>
+where
+    F: FnOnce(&mut RandomSource) -> Result<Signature, CryptoError>,
+{
+    if options.hedged_nonce {
+        with_source(source, sign)
+    } else {
+        sign(&mut RandomSource::os())
+    }
 }
 
 ///Returns private key length in bytes
@@ -81,6 +1774,12 @@ pub extern "C" fn get_public_key_length() -> c_int {
     constants::PUBLIC_KEY_LENGTH as i32
 }
 
+///Returns prehash length in bytes
+#[no_mangle]
+pub extern "C" fn get_prehash_length() -> c_int {
+    constants::PREHASH_LENGTH as i32
+}
+
 ///Returns signature length in bytes
 #[no_mangle]
 pub extern "C" fn get_signature_length() -> c_int {
@@ -98,6 +1797,7 @@ mod tests {
     use super::*;
     use hex::FromHex;
     use protobuf::RepeatedField;
+    use rand::rngs::OsRng;
 
     #[test]
     fn can_create_signature() {
@@ -106,18 +1806,23 @@ mod tests {
         let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
         let message = b"message";
         let context = b"context";
-        let result = std_sign(
-            &mut sig,
-            &mut public_key,
-            &private_key,
-            message.as_ptr(),
-            message.len(),
-            context.as_ptr(),
-            context.len(),
-        );
-        assert_eq!(result, ErrorCode::NO_ERROR.value());
-    }
-
+        let result = unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+    }
+
     #[test]
     fn can_sign_message_and_verify_signature() {
         let mut sig = [0u8; constants::SIGNATURE_LENGTH];
@@ -126,344 +1831,1763 @@ mod tests {
 
         let message = b"message";
         let context = b"Context 1 2 3";
-        std_sign(
-            &mut sig,
-            &mut public_key,
-            &private_key,
-            message.as_ptr(),
-            message.len(),
-            context.as_ptr(),
-            context.len(),
+        unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+
+        assert_eq!(
+            unsafe {
+                std_verify(
+                    sig.as_ptr(),
+                    sig.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            },
+            ErrorCode::NO_ERROR.value()
+        );
+    }
+
+    #[test]
+    fn verification_fails_for_incorrect_context() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+
+        let message = b"message";
+        let context1 = b"Context 1 2 3";
+        let context2 = b"Context 1 2 3 4";
+        unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context1.as_ptr(),
+                context1.len(),
+            )
+        };
+        let verified = unsafe {
+            std_verify(
+                sig.as_ptr(),
+                sig.len(),
+                public_key.as_ptr(),
+                public_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context2.as_ptr(),
+                context2.len(),
+            )
+        };
+        assert_eq!(verified, ErrorCode::SIGNATURE_VERIFICATION_FAILURE.value())
+    }
+
+    #[test]
+    fn signing_fails_for_context_length_exceeding_maximum() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+
+        let message = b"message";
+        let context = b"2hPB7lVGQHENtQLcfOoTnEjBHO5jg0zgyQYyrKHOxywDrDDfmyg0z9M9Q0hRRfPUV4fWvRGR9l48a3cXmFqBPneErN5GwzD28E3cLhDRNAdaNEpelPRDzN4w2dGaNWc4Jrc7TlVEbC5JQdfMgmtPkakmF3mPCU1YUFQArFUbQFQdFLHL2PByvyzdHaStkSgZbCz0zb9jCBO0vwx4J6YXvXFoc9urYREcR7uiFEVcrf6L2C2uUVOtWQUHRQyIRtmx";
+        let result = unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+
+        assert_eq!(result, ErrorCode::INVALID_CONTEXT_LENGTH.value())
+    }
+
+    #[test]
+    fn verification_fails_for_invalid_public_key() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+
+        let message = b"message";
+        let context = b"Context 1 2 3";
+        unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+        public_key[constants::PUBLIC_KEY_LENGTH - 1] =
+            public_key[constants::PUBLIC_KEY_LENGTH - 1].wrapping_add(1u8);
+        let verified = unsafe {
+            std_verify(
+                sig.as_ptr(),
+                sig.len(),
+                public_key.as_ptr(),
+                public_key.len(),
+                message.as_ptr(),
+                message.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+        assert_eq!(verified, ErrorCode::INVALID_PUBLIC_KEY.value())
+    }
+
+    #[test]
+    fn can_validate_using_known_test_vector() {
+        let sig = <[u8; constants::SIGNATURE_LENGTH]>::from_hex("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406").unwrap();
+        let public_key = <[u8; constants::PRIVATE_KEY_LENGTH]>::from_hex(
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+        )
+        .unwrap();
+        let mut message = [0u8; 3];
+        hex::decode_to_slice("616263", &mut message as &mut [u8]).unwrap();
+        let context = b"";
+        assert_eq!(
+            unsafe {
+                std_verify(
+                    sig.as_ptr(),
+                    sig.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            },
+            ErrorCode::NO_ERROR.value()
+        );
+    }
+
+    #[test]
+    fn can_sign_message_and_verify_signature_with_empty_context() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+
+        let message = b"message";
+        let context = b"";
+        assert_eq!(
+            unsafe {
+                std_sign(
+                    sig.as_mut_ptr(),
+                    sig.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            },
+            ErrorCode::NO_ERROR.value()
+        );
+
+        assert_eq!(
+            unsafe {
+                std_verify(
+                    sig.as_ptr(),
+                    sig.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            },
+            ErrorCode::NO_ERROR.value()
+        );
+    }
+
+    #[test]
+    fn can_validate_rfc8032_test_vector_for_each_mode() {
+        let vectors = [
+            (
+                SignatureMode::Ed25519,
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "72",
+                "",
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+            (
+                SignatureMode::Ed25519ctx,
+                "0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6",
+                "dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292",
+                "f726936d19c800494e3fdaff20b276a8",
+                "666f6f",
+                "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d",
+            ),
+            (
+                SignatureMode::Ed25519ph,
+                "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+                "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+                "616263",
+                "",
+                "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406",
+            ),
+        ];
+
+        for (mode, private_key, public_key, message, context, signature) in vectors.iter() {
+            let private_key = <[u8; constants::PRIVATE_KEY_LENGTH]>::from_hex(private_key).unwrap();
+            let public_key = <[u8; constants::PUBLIC_KEY_LENGTH]>::from_hex(public_key).unwrap();
+            let signature = <[u8; constants::SIGNATURE_LENGTH]>::from_hex(signature).unwrap();
+            let message = Vec::from_hex(message).unwrap();
+            let context = Vec::from_hex(context).unwrap();
+
+            let mut out_sig = [0u8; constants::SIGNATURE_LENGTH];
+            let mut out_public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+            assert_eq!(
+                unsafe {
+                    std_sign_with_mode(
+                        out_sig.as_mut_ptr(),
+                        out_sig.len(),
+                        out_public_key.as_mut_ptr(),
+                        out_public_key.len(),
+                        private_key.as_ptr(),
+                        private_key.len(),
+                        message.as_ptr(),
+                        message.len(),
+                        context.as_ptr(),
+                        context.len(),
+                        *mode as c_int,
+                    )
+                },
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(out_sig[..], signature[..]);
+            assert_eq!(out_public_key, public_key);
+
+            assert_eq!(
+                unsafe {
+                    std_verify_with_mode(
+                        signature.as_ptr(),
+                        signature.len(),
+                        public_key.as_ptr(),
+                        public_key.len(),
+                        message.as_ptr(),
+                        message.len(),
+                        context.as_ptr(),
+                        context.len(),
+                        *mode as c_int,
+                    )
+                },
+                ErrorCode::NO_ERROR.value()
+            );
+        }
+    }
+
+    #[test]
+    fn verification_fails_for_unknown_signature_mode() {
+        let sig = [0u8; constants::SIGNATURE_LENGTH];
+        let public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let message = b"message";
+        let context = b"";
+        assert_eq!(
+            unsafe {
+                std_verify_with_mode(
+                    sig.as_ptr(),
+                    sig.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                    3,
+                )
+            },
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
+        );
+    }
+
+    #[test]
+    fn signing_fails_for_null_private_key() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let message = b"message";
+        let result = unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                std::ptr::null(),
+                constants::PRIVATE_KEY_LENGTH,
+                message.as_ptr(),
+                message.len(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(result, ErrorCode::INVALID_PRIVATE_KEY.value());
+    }
+
+    #[test]
+    fn signing_fails_for_short_signature_buffer() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH - 1];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"message";
+        let result = unsafe {
+            std_sign(
+                sig.as_mut_ptr(),
+                sig.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                message.as_ptr(),
+                message.len(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(result, ErrorCode::INVALID_SIGNATURE.value());
+    }
+
+    #[test]
+    fn can_sign_and_verify_empty_message_passed_as_null() {
+        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        unsafe {
+            assert_eq!(
+                std_sign(
+                    sig.as_mut_ptr(),
+                    sig.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null(),
+                    0,
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                std_verify(
+                    sig.as_ptr(),
+                    sig.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null(),
+                    0,
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+        }
+    }
+
+    #[test]
+    fn verification_fails_for_null_message_of_non_zero_length() {
+        let sig = [0u8; constants::SIGNATURE_LENGTH];
+        let public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let result = unsafe {
+            std_verify(
+                sig.as_ptr(),
+                sig.len(),
+                public_key.as_ptr(),
+                public_key.len(),
+                std::ptr::null(),
+                7,
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(result, ErrorCode::ERROR_CODE_UNKNOWN.value());
+    }
+
+    #[test]
+    fn validation_fails_for_null_or_short_public_key() {
+        let public_key = [0u8; constants::PUBLIC_KEY_LENGTH - 1];
+        unsafe {
+            assert_eq!(
+                validate_public_key(std::ptr::null(), constants::PUBLIC_KEY_LENGTH),
+                ErrorCode::INVALID_PUBLIC_KEY.value()
+            );
+            assert_eq!(
+                validate_public_key(public_key.as_ptr(), public_key.len()),
+                ErrorCode::INVALID_PUBLIC_KEY.value()
+            );
+        }
+    }
+
+    #[test]
+    fn batch_verify_fails_for_null_batch() {
+        let result = unsafe { verify_batch(std::ptr::null(), 10) };
+        assert_eq!(result, ErrorCode::INVALID_BATCH_MESSAGE.value());
+    }
+
+    #[test]
+    fn batch_verify_fails_for_truncated_batch() {
+        let batch = signed_batch(&[b"first", b"second"])
+            .write_to_bytes()
+            .unwrap();
+        let truncated = &batch[..batch.len() - 1];
+
+        let result = unsafe { verify_batch(truncated.as_ptr(), truncated.len()) };
+        assert_eq!(result, ErrorCode::INVALID_BATCH_MESSAGE.value());
+
+        let mut results = [0u8; 2];
+        let result = unsafe {
+            verify_batch_each(
+                truncated.as_ptr(),
+                truncated.len(),
+                results.as_mut_ptr(),
+                results.len(),
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(result, ErrorCode::INVALID_BATCH_MESSAGE.value());
+    }
+
+    #[test]
+    fn guard_reports_panic_as_unknown_error() {
+        let result = guard(|| panic!("boom"));
+        assert_eq!(result, ErrorCode::ERROR_CODE_UNKNOWN.value());
+    }
+
+    #[test]
+    fn batch_verify_returns_error_for_malformed_signature() {
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context".to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(vec![b"message".to_vec()]));
+        batch_sigs.set_signatures(RepeatedField::from_vec(vec![vec![0u8; 10]]));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(vec![vec![
+            0u8;
+            constants::PUBLIC_KEY_LENGTH
+        ]]));
+        let batch = batch_sigs.write_to_bytes().unwrap();
+
+        let result = unsafe { verify_batch(batch.as_ptr(), batch.len()) };
+
+        assert_eq!(result, ErrorCode::INVALID_SIGNATURE.value());
+    }
+
+    #[test]
+    fn batch_verify_validates_multiple_correct_signatures() {
+        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
+        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
+        let messages = vec![
+            b"'Twas brillig, and the slithy toves".to_vec(),
+            b"Did gyre and gimble in the wabe:".to_vec(),
+            b"All mimsy were the borogoves,".to_vec(),
+            b"And the mome raths outgrabe.".to_vec(),
+            b"'Beware the Jabberwock, my son!".to_vec(),
+        ];
+        let context = b"context";
+
+        for message in messages.iter() {
+            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+            unsafe { generate_private_key(private_key.as_mut_ptr(), private_key.len()) };
+
+            unsafe {
+                std_sign(
+                    sig.as_mut_ptr(),
+                    sig.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            };
+            sigs.push(sig.to_vec());
+            public_keys.push(public_key.to_vec());
+        }
+
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context".to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(messages));
+        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
+        let batch = batch_sigs.write_to_bytes().unwrap();
+
+        let result = unsafe { verify_batch(batch.as_ptr(), batch.len()) };
+
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+    }
+
+    #[test]
+    fn batch_verify_fails_on_single_incorrect_message() {
+        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
+        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
+        let mut messages = vec![
+            b"'Twas brillig, and the slithy toves".to_vec(),
+            b"Did gyre and gimble in the wabe:".to_vec(),
+            b"All mimsy were the borogoves,".to_vec(),
+            b"And the mome raths outgrabe.".to_vec(),
+            b"'Beware the Jabberwock, my son!".to_vec(),
+        ];
+        let context = b"context";
+
+        for message in messages.iter() {
+            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+            unsafe { generate_private_key(private_key.as_mut_ptr(), private_key.len()) };
+
+            unsafe {
+                std_sign(
+                    sig.as_mut_ptr(),
+                    sig.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            };
+            sigs.push(sig.to_vec());
+            public_keys.push(public_key.to_vec());
+        }
+        //alter a message before batch verification
+        messages[4] = b"The jaws that bite, the claws that catch!".to_vec();
+
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context".to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(messages));
+        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
+        let batch = batch_sigs.write_to_bytes().unwrap();
+
+        let result = unsafe { verify_batch(batch.as_ptr(), batch.len()) };
+
+        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+    }
+
+    #[test]
+    fn batch_verify_fails_on_single_incorrect_signature() {
+        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
+        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
+        let messages = vec![
+            b"'Twas brillig, and the slithy toves".to_vec(),
+            b"Did gyre and gimble in the wabe:".to_vec(),
+            b"All mimsy were the borogoves,".to_vec(),
+            b"And the mome raths outgrabe.".to_vec(),
+            b"'Beware the Jabberwock, my son!".to_vec(),
+        ];
+        let context = b"context";
+
+        for message in messages.iter() {
+            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+            unsafe { generate_private_key(private_key.as_mut_ptr(), private_key.len()) };
+
+            unsafe {
+                std_sign(
+                    sig.as_mut_ptr(),
+                    sig.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            };
+            sigs.push(sig.to_vec());
+            public_keys.push(public_key.to_vec());
+        }
+        //alter a signature before batch verification
+        sigs[3] = sigs[4].to_owned();
+
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context".to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(messages));
+        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
+        let batch = batch_sigs.write_to_bytes().unwrap();
+
+        let result = unsafe { verify_batch(batch.as_ptr(), batch.len()) };
+
+        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+    }
+
+    #[test]
+    fn batch_verify_fails_on_incorrect_context() {
+        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
+        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
+        let messages = vec![
+            b"'Twas brillig, and the slithy toves".to_vec(),
+            b"Did gyre and gimble in the wabe:".to_vec(),
+            b"All mimsy were the borogoves,".to_vec(),
+            b"And the mome raths outgrabe.".to_vec(),
+            b"'Beware the Jabberwock, my son!".to_vec(),
+        ];
+        let context = b"context";
+
+        for message in messages.iter() {
+            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+            unsafe { generate_private_key(private_key.as_mut_ptr(), private_key.len()) };
+
+            unsafe {
+                std_sign(
+                    sig.as_mut_ptr(),
+                    sig.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                )
+            };
+            sigs.push(sig.to_vec());
+            public_keys.push(public_key.to_vec());
+        }
+
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context2".to_vec());
+        batch_sigs.set_messages(RepeatedField::from_vec(messages));
+        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
+        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
+        let batch = batch_sigs.write_to_bytes().unwrap();
+
+        let result = unsafe { verify_batch(batch.as_ptr(), batch.len()) };
+
+        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+    }
+
+    fn signed_batch(messages: &[&[u8]]) -> SignatureBatch {
+        let mut batch_sigs = SignatureBatch::new();
+        batch_sigs.set_context(b"context".to_vec());
+        for message in messages {
+            let key = SigningKey::generate(&mut OsRng {});
+            let signature = key.sign(message, b"context").unwrap();
+            batch_sigs.mut_messages().push(message.to_vec());
+            batch_sigs
+                .mut_signatures()
+                .push(signature.to_bytes().to_vec());
+            batch_sigs
+                .mut_public_keys()
+                .push(key.verifying_key().to_bytes().to_vec());
+        }
+        batch_sigs
+    }
+
+    fn call_sign_batch(
+        out_batch: Option<&mut [u8]>,
+        private_keys: &[u8],
+        messages: &[&[u8]],
+    ) -> (c_int, usize) {
+        let concatenated = messages.concat();
+        let mut offsets = vec![0u64];
+        for message in messages {
+            offsets.push(offsets[offsets.len() - 1] + message.len() as u64);
+        }
+        let out_batch_length = out_batch.as_ref().map_or(0, |out| out.len());
+        let mut written = 0usize;
+        let result = unsafe {
+            sign_batch(
+                out_batch.map_or(std::ptr::null_mut(), |out| out.as_mut_ptr()),
+                out_batch_length,
+                &mut written,
+                private_keys.as_ptr(),
+                private_keys.len(),
+                concatenated.as_ptr(),
+                concatenated.len(),
+                offsets.as_ptr(),
+                messages.len(),
+                b"context".as_ptr(),
+                b"context".len(),
+            )
+        };
+        (result, written)
+    }
+
+    #[test]
+    fn signed_batch_verifies() {
+        let messages: [&[u8]; 3] = [b"first", b"", b"third"];
+        let mut private_keys = Vec::new();
+        for _ in 0..messages.len() {
+            private_keys.extend_from_slice(&SigningKey::generate(&mut OsRng {}).to_bytes());
+        }
+
+        for keys in [
+            &private_keys[..constants::PRIVATE_KEY_LENGTH],
+            &private_keys[..],
+        ]
+        .iter()
+        {
+            let mut out_batch = [0u8; 1024];
+            let (result, written) = call_sign_batch(Some(&mut out_batch), keys, &messages);
+            assert_eq!(result, ErrorCode::NO_ERROR.value());
+
+            let mut batch_sigs = SignatureBatch::new();
+            batch_sigs.merge_from_bytes(&out_batch[..written]).unwrap();
+            assert_eq!(batch_sigs.get_messages()[2], b"third".to_vec());
+            let result = unsafe { verify_batch(out_batch.as_ptr(), written) };
+            assert_eq!(result, ErrorCode::NO_ERROR.value());
+        }
+    }
+
+    #[test]
+    fn sign_batch_reports_length_for_null_buffer() {
+        let private_key = SigningKey::generate(&mut OsRng {}).to_bytes();
+        let messages: [&[u8]; 2] = [b"first", b"second"];
+
+        let (result, length) = call_sign_batch(None, &private_key, &messages);
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+
+        let mut out_batch = vec![0u8; length - 1];
+        let (result, written) = call_sign_batch(Some(&mut out_batch), &private_key, &messages);
+        assert_eq!(result, ErrorCode::INVALID_BATCH_MESSAGE.value());
+        assert_eq!(written, length);
+        assert!(out_batch.iter().all(|byte| *byte == 0));
+
+        let mut out_batch = vec![0u8; length];
+        let (result, written) = call_sign_batch(Some(&mut out_batch), &private_key, &messages);
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        assert_eq!(written, length);
+        let result = unsafe { verify_batch(out_batch.as_ptr(), written) };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+    }
+
+    #[test]
+    fn sign_batch_fails_for_wrong_private_keys_length() {
+        let mut out_batch = [0u8; 1024];
+        let private_keys = [0u8; 2 * constants::PRIVATE_KEY_LENGTH];
+        let messages: [&[u8]; 3] = [b"first", b"second", b"third"];
+
+        let (result, _) = call_sign_batch(Some(&mut out_batch), &private_keys, &messages);
+
+        assert_eq!(result, ErrorCode::INVALID_PRIVATE_KEY.value());
+    }
+
+    #[test]
+    fn batch_verify_with_transcript_randomness() {
+        let mut batch_sigs = signed_batch(&[b"first", b"second", b"third"]);
+        let verify = |batch: &[u8], randomness: c_int, extra_entropy: &[u8]| unsafe {
+            verify_batch_with_randomness(
+                batch.as_ptr(),
+                batch.len(),
+                randomness,
+                extra_entropy.as_ptr(),
+                extra_entropy.len(),
+                std::ptr::null_mut(),
+            )
+        };
+
+        let batch = batch_sigs.write_to_bytes().unwrap();
+        assert_eq!(verify(&batch, 0, b""), ErrorCode::NO_ERROR.value());
+        assert_eq!(verify(&batch, 1, b""), ErrorCode::NO_ERROR.value());
+        assert_eq!(verify(&batch, 1, b"extra"), ErrorCode::NO_ERROR.value());
+        assert_eq!(
+            verify(&batch, 2, b""),
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
+        );
+
+        batch_sigs.mut_messages()[1] = b"altered".to_vec();
+        let batch = batch_sigs.write_to_bytes().unwrap();
+        assert_eq!(
+            verify(&batch, 1, b"extra"),
+            ErrorCode::BATCH_VERIFICATION_FAILURE.value()
+        );
+    }
+
+    #[test]
+    fn batch_verifier_handle_verifies_added_signatures() {
+        let verifier = batch_verifier_new();
+        let context = b"context";
+        let add = |signature: &[u8], publickey: &[u8], message: &[u8]| unsafe {
+            batch_verifier_add(
+                verifier,
+                signature.as_ptr(),
+                signature.len(),
+                publickey.as_ptr(),
+                publickey.len(),
+                message.as_ptr(),
+                message.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+
+        for message in [&b"first"[..], b"second"].iter() {
+            let key = SigningKey::generate(&mut OsRng {});
+            let signature = key.sign(message, context).unwrap().to_bytes();
+            let publickey = key.verifying_key().to_bytes();
+            assert_eq!(
+                add(&signature, &publickey, message),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                add(&signature[1..], &publickey, message),
+                ErrorCode::INVALID_SIGNATURE.value()
+            );
+        }
+        assert_eq!(
+            unsafe { batch_verifier_verify(verifier, std::ptr::null_mut()) },
+            ErrorCode::NO_ERROR.value()
+        );
+
+        let key = SigningKey::generate(&mut OsRng {});
+        let signature = key.sign(b"third", context).unwrap().to_bytes();
+        add(&signature, &key.verifying_key().to_bytes(), b"altered");
+        assert_eq!(
+            unsafe { batch_verifier_verify(verifier, std::ptr::null_mut()) },
+            ErrorCode::BATCH_VERIFICATION_FAILURE.value()
+        );
+
+        unsafe { batch_verifier_free(verifier) };
+    }
+
+    #[test]
+    fn batch_verifier_handle_rejects_null() {
+        let result = unsafe {
+            batch_verifier_add(
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(result, ErrorCode::ERROR_CODE_UNKNOWN.value());
+        assert_eq!(
+            unsafe { batch_verifier_verify(std::ptr::null(), std::ptr::null_mut()) },
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
+        );
+        unsafe { batch_verifier_free(std::ptr::null_mut()) };
+    }
+
+    #[test]
+    fn streamed_message_can_be_signed_and_verified() {
+        let private_key = [3u8; constants::PRIVATE_KEY_LENGTH];
+        let message: Vec<u8> = (0..4096u32).map(|i| i as u8).collect();
+        let context = b"snapshot";
+
+        let mut signer = std::ptr::null_mut();
+        let mut verifier = std::ptr::null_mut();
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        unsafe {
+            assert_eq!(
+                sign_stream_init(&mut signer, context.as_ptr(), context.len()),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                verify_stream_init(&mut verifier, context.as_ptr(), context.len()),
+                ErrorCode::NO_ERROR.value()
+            );
+            for chunk in message.chunks(1000) {
+                sign_stream_update(signer, chunk.as_ptr(), chunk.len());
+                verify_stream_update(verifier, chunk.as_ptr(), chunk.len());
+            }
+            assert_eq!(
+                sign_stream_final(
+                    signer,
+                    signature.as_mut_ptr(),
+                    signature.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                verify_stream_final(
+                    verifier,
+                    signature.as_ptr(),
+                    signature.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+
+            verify_stream_update(verifier, b"appended".as_ptr(), 8);
+            assert_eq!(
+                verify_stream_final(
+                    verifier,
+                    signature.as_ptr(),
+                    signature.len(),
+                    public_key.as_ptr(),
+                    public_key.len(),
+                ),
+                ErrorCode::SIGNATURE_VERIFICATION_FAILURE.value()
+            );
+            sign_stream_free(signer);
+            verify_stream_free(verifier);
+        }
+
+        let (expected, _) = std_signature::try_sign(&private_key, &message, context).unwrap();
+        assert_eq!(signature, expected);
+    }
+
+    #[test]
+    fn keypair_handle_signs_as_std_sign() {
+        let private_key = [5u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"message";
+        let context = b"context";
+        let mut keypair = std::ptr::null_mut();
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        let mut exported = [0u8; constants::PRIVATE_KEY_LENGTH];
+        unsafe {
+            assert_eq!(
+                keypair_from_private_key(&mut keypair, private_key.as_ptr(), private_key.len()),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                keypair_public_key(keypair, public_key.as_mut_ptr(), public_key.len()),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                keypair_sign(
+                    keypair,
+                    signature.as_mut_ptr(),
+                    signature.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+            assert_eq!(
+                keypair_export_private_key(keypair, exported.as_mut_ptr(), exported.len()),
+                ErrorCode::NO_ERROR.value()
+            );
+            keypair_free(keypair);
+        }
+
+        let (expected_signature, expected_public_key) =
+            std_signature::try_sign(&private_key, message, context).unwrap();
+        assert_eq!(signature.to_vec(), expected_signature.to_vec());
+        assert_eq!(public_key, expected_public_key);
+        assert_eq!(exported, private_key);
+    }
+
+    #[test]
+    fn generated_keypair_signatures_verify() {
+        let mut keypair = std::ptr::null_mut();
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        let mut short = [0u8; constants::PRIVATE_KEY_LENGTH - 1];
+        unsafe {
+            assert_eq!(
+                keypair_generate(&mut keypair, std::ptr::null_mut()),
+                ErrorCode::NO_ERROR.value()
+            );
+            keypair_public_key(keypair, public_key.as_mut_ptr(), public_key.len());
+            keypair_sign(
+                keypair,
+                signature.as_mut_ptr(),
+                signature.len(),
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+                0,
+            );
+            assert_eq!(
+                keypair_export_private_key(keypair, short.as_mut_ptr(), short.len()),
+                ErrorCode::INVALID_PRIVATE_KEY.value()
+            );
+            keypair_free(keypair);
+        }
+        assert_eq!(
+            std_signature::try_verify(&signature, &public_key, b"", b""),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn hedged_signatures_verify() {
+        let private_key = [9u8; constants::PRIVATE_KEY_LENGTH];
+        let message = b"block";
+        let context = b"validator";
+        let sign = |options_flags: c_uint| {
+            let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+            let result = unsafe {
+                std_sign_with_options(
+                    signature.as_mut_ptr(),
+                    signature.len(),
+                    public_key.as_mut_ptr(),
+                    public_key.len(),
+                    private_key.as_ptr(),
+                    private_key.len(),
+                    message.as_ptr(),
+                    message.len(),
+                    context.as_ptr(),
+                    context.len(),
+                    SignatureMode::Ed25519ph as c_int,
+                    options_flags,
+                    std::ptr::null_mut(),
+                )
+            };
+            (result, signature, public_key)
+        };
+
+        let flags = signing::HEDGED_NONCE | signing::VERIFY_AFTER_SIGN;
+        let (result, first, public_key) = sign(flags);
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        let (_, second, _) = sign(flags);
+        assert_ne!(first.to_vec(), second.to_vec());
+        for signature in [first, second].iter() {
+            assert_eq!(
+                std_signature::try_verify(signature, &public_key, message, context),
+                Ok(())
+            );
+        }
+
+        let (result, deterministic, _) = sign(0);
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        let (expected, _) = std_signature::try_sign(&private_key, message, context).unwrap();
+        assert_eq!(deterministic.to_vec(), expected.to_vec());
+        assert_eq!(sign(1 << 5).0, ErrorCode::ERROR_CODE_UNKNOWN.value());
+    }
+
+    #[test]
+    fn keypair_signs_with_options() {
+        let mut keypair = std::ptr::null_mut();
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        unsafe {
+            keypair_generate(&mut keypair, std::ptr::null_mut());
+            keypair_public_key(keypair, public_key.as_mut_ptr(), public_key.len());
+            assert_eq!(
+                keypair_sign_with_options(
+                    keypair,
+                    signature.as_mut_ptr(),
+                    signature.len(),
+                    b"block".as_ptr(),
+                    5,
+                    std::ptr::null(),
+                    0,
+                    signing::HEDGED_NONCE | signing::VERIFY_AFTER_SIGN,
+                    std::ptr::null_mut()
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+            keypair_free(keypair);
+        }
+        assert_eq!(
+            std_signature::try_verify(&signature, &public_key, b"block", b""),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn signing_with_policy_checks_flags_and_matches_plain_signing() {
+        let private_key = [5u8; constants::PRIVATE_KEY_LENGTH];
+        let mut keypair = std::ptr::null_mut();
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        let mut keypair_signature = [0u8; constants::SIGNATURE_LENGTH];
+        let sign = |signature: &mut [u8], public_key: &mut [u8], flags: c_uint| unsafe {
+            std_sign_with_policy(
+                signature.as_mut_ptr(),
+                signature.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                b"block".as_ptr(),
+                5,
+                b"context".as_ptr(),
+                7,
+                SignatureMode::Ed25519ph as c_int,
+                flags,
+            )
+        };
+        assert_eq!(
+            sign(&mut signature, &mut public_key, 1 << 31),
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
         );
-
         assert_eq!(
-            std_verify(
-                &sig,
-                &public_key,
-                message.as_ptr(),
-                message.len(),
-                context.as_ptr(),
-                context.len(),
+            sign(
+                &mut signature,
+                &mut public_key,
+                VerificationPolicy::strict().to_flags()
             ),
             ErrorCode::NO_ERROR.value()
         );
+        unsafe {
+            keypair_from_private_key(&mut keypair, private_key.as_ptr(), private_key.len());
+            assert_eq!(
+                keypair_sign_with_policy(
+                    keypair,
+                    keypair_signature.as_mut_ptr(),
+                    keypair_signature.len(),
+                    b"block".as_ptr(),
+                    5,
+                    b"context".as_ptr(),
+                    7,
+                    VerificationPolicy::strict().to_flags(),
+                ),
+                ErrorCode::NO_ERROR.value()
+            );
+            keypair_free(keypair);
+        }
+
+        let expected = std_signature::try_sign(&private_key, b"block", b"context").unwrap();
+        assert_eq!((signature, public_key), expected);
+        assert_eq!(keypair_signature, expected.0);
     }
 
     #[test]
-    fn verification_fails_for_incorrect_context() {
-        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+    fn keypair_functions_reject_null() {
         let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        unsafe {
+            assert_eq!(
+                keypair_from_private_key(
+                    std::ptr::null_mut(),
+                    private_key.as_ptr(),
+                    private_key.len()
+                ),
+                ErrorCode::ERROR_CODE_UNKNOWN.value()
+            );
+            assert_eq!(
+                keypair_sign(
+                    std::ptr::null(),
+                    signature.as_mut_ptr(),
+                    signature.len(),
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null(),
+                    0,
+                ),
+                ErrorCode::ERROR_CODE_UNKNOWN.value()
+            );
+            keypair_free(std::ptr::null_mut());
+        }
+    }
 
-        let message = b"message";
-        let context1 = b"Context 1 2 3";
-        let context2 = b"Context 1 2 3 4";
-        std_sign(
-            &mut sig,
-            &mut public_key,
-            &private_key,
-            message.as_ptr(),
-            message.len(),
-            context1.as_ptr(),
-            context1.len(),
-        );
-        let verified = std_verify(
-            &sig,
-            &public_key,
-            message.as_ptr(),
-            message.len(),
-            context2.as_ptr(),
-            context2.len(),
+    #[test]
+    fn stream_init_fails_for_long_context() {
+        let context = [0u8; constants::CONTEXT_MAX_LENGTH + 1];
+        let mut signer = std::ptr::null_mut();
+        let result = unsafe { sign_stream_init(&mut signer, context.as_ptr(), context.len()) };
+        assert_eq!(result, ErrorCode::INVALID_CONTEXT_LENGTH.value());
+        assert!(signer.is_null());
+        assert_eq!(
+            unsafe { sign_stream_update(std::ptr::null_mut(), std::ptr::null(), 0) },
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
         );
-        assert_eq!(verified, ErrorCode::SIGNATURE_VERIFICATION_FAILURE.value())
+        unsafe { verify_stream_free(std::ptr::null_mut()) };
     }
 
-    #[test]
-    fn signing_fails_for_context_length_exceeding_maximum() {
-        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
-        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+    struct FlatBatch {
+        signatures: Vec<u8>,
+        public_keys: Vec<u8>,
+        messages: Vec<u8>,
+        message_offsets: Vec<u64>,
+        contexts: Vec<u8>,
+        context_offsets: Vec<u64>,
+    }
 
-        let message = b"message";
-        let context = b"2hPB7lVGQHENtQLcfOoTnEjBHO5jg0zgyQYyrKHOxywDrDDfmyg0z9M9Q0hRRfPUV4fWvRGR9l48a3cXmFqBPneErN5GwzD28E3cLhDRNAdaNEpelPRDzN4w2dGaNWc4Jrc7TlVEbC5JQdfMgmtPkakmF3mPCU1YUFQArFUbQFQdFLHL2PByvyzdHaStkSgZbCz0zb9jCBO0vwx4J6YXvXFoc9urYREcR7uiFEVcrf6L2C2uUVOtWQUHRQyIRtmx";
-        let result = std_sign(
-            &mut sig,
-            &mut public_key,
-            &private_key,
-            message.as_ptr(),
-            message.len(),
-            context.as_ptr(),
-            context.len(),
-        );
+    impl FlatBatch {
+        /// Signs each message under its own context.
+        fn new(entries: &[(&[u8], &[u8])]) -> Self {
+            let mut batch = FlatBatch {
+                signatures: Vec::new(),
+                public_keys: Vec::new(),
+                messages: Vec::new(),
+                message_offsets: vec![0],
+                contexts: Vec::new(),
+                context_offsets: vec![0],
+            };
+            for (message, context) in entries {
+                let key = SigningKey::generate(&mut OsRng {});
+                let signature = key.sign(message, context).unwrap();
+                batch.signatures.extend_from_slice(&signature.to_bytes());
+                batch
+                    .public_keys
+                    .extend_from_slice(key.verifying_key().as_bytes());
+                batch.messages.extend_from_slice(message);
+                batch.message_offsets.push(batch.messages.len() as u64);
+                batch.contexts.extend_from_slice(context);
+                batch.context_offsets.push(batch.contexts.len() as u64);
+            }
+            batch
+        }
 
-        assert_eq!(result, ErrorCode::INVALID_CONTEXT_LENGTH.value())
+        /// Verifies with per-entry contexts, or with `shared_context` if given.
+        fn verify(&self, shared_context: Option<&[u8]>, out_results: Option<&mut [u8]>) -> c_int {
+            let (contexts, context_offsets) = match shared_context {
+                Some(context) => (context, std::ptr::null()),
+                None => (&self.contexts[..], self.context_offsets.as_ptr()),
+            };
+            let (out_results, out_results_length) = match out_results {
+                Some(out_results) => (out_results.as_mut_ptr(), out_results.len()),
+                None => (std::ptr::null_mut(), 0),
+            };
+            unsafe {
+                verify_batch_arrays(
+                    self.signatures.as_ptr(),
+                    self.signatures.len(),
+                    self.public_keys.as_ptr(),
+                    self.public_keys.len(),
+                    self.messages.as_ptr(),
+                    self.messages.len(),
+                    self.message_offsets.as_ptr(),
+                    contexts.as_ptr(),
+                    contexts.len(),
+                    context_offsets,
+                    self.message_offsets.len() - 1,
+                    out_results,
+                    out_results_length,
+                    std::ptr::null_mut(),
+                )
+            }
+        }
     }
 
     #[test]
-    fn verification_fails_for_invalid_public_key() {
-        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
-        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+    fn batch_verify_arrays_with_per_entry_contexts() {
+        let mut batch = FlatBatch::new(&[(b"first", b"one"), (b"", b""), (b"third", b"three")]);
+        assert_eq!(batch.verify(None, None), ErrorCode::NO_ERROR.value());
 
-        let message = b"message";
-        let context = b"Context 1 2 3";
-        std_sign(
-            &mut sig,
-            &mut public_key,
-            &private_key,
-            message.as_ptr(),
-            message.len(),
-            context.as_ptr(),
-            context.len(),
+        let mut results = [0xffu8; 3];
+        assert_eq!(
+            batch.verify(None, Some(&mut results)),
+            ErrorCode::NO_ERROR.value()
         );
-        public_key[constants::PUBLIC_KEY_LENGTH - 1] =
-            public_key[constants::PUBLIC_KEY_LENGTH - 1].wrapping_add(1u8);
-        let verified = std_verify(
-            &sig,
-            &public_key,
-            message.as_ptr(),
-            message.len(),
-            context.as_ptr(),
-            context.len(),
+        assert_eq!(results, [1, 1, 1]);
+
+        batch.messages[0] ^= 1;
+        assert_eq!(
+            batch.verify(None, None),
+            ErrorCode::BATCH_VERIFICATION_FAILURE.value()
         );
-        assert_eq!(verified, ErrorCode::INVALID_PUBLIC_KEY.value())
+        assert_eq!(
+            batch.verify(None, Some(&mut results)),
+            ErrorCode::BATCH_VERIFICATION_FAILURE.value()
+        );
+        assert_eq!(results, [0, 1, 1]);
     }
 
     #[test]
-    fn can_validate_using_known_test_vector() {
-        let sig = <[u8; constants::SIGNATURE_LENGTH]>::from_hex("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406").unwrap();
-        let public_key = <[u8; constants::PRIVATE_KEY_LENGTH]>::from_hex(
-            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
-        )
-        .unwrap();
-        let mut message = [0u8; 3];
-        hex::decode_to_slice("616263", &mut message as &mut [u8]).unwrap();
-        let context = b"";
+    fn batch_verify_arrays_with_shared_context() {
+        let batch = FlatBatch::new(&[(b"first", b"shared"), (b"second", b"shared")]);
         assert_eq!(
-            std_verify(
-                &sig,
-                &public_key,
-                message.as_ptr(),
-                message.len(),
-                context.as_ptr(),
-                context.len(),
-            ),
+            batch.verify(Some(b"shared"), None),
             ErrorCode::NO_ERROR.value()
         );
+        assert_eq!(
+            batch.verify(Some(b"other"), None),
+            ErrorCode::BATCH_VERIFICATION_FAILURE.value()
+        );
     }
 
-    #[test]
-    fn can_sign_message_and_verify_signature_with_empty_context() {
-        let mut sig = [0u8; constants::SIGNATURE_LENGTH];
+    /// SHA-512 of "abc", the message of the RFC 8032 Ed25519ph test vector.
+    const ABC_PREHASH: &str = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+
+    fn sign_prehashed(private_key: &[u8], prehash: &[u8], context: &[u8]) -> ([u8; 64], [u8; 32]) {
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
         let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
-        let private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let result = unsafe {
+            std_sign_prehashed(
+                signature.as_mut_ptr(),
+                signature.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                prehash.as_ptr(),
+                prehash.len(),
+                context.as_ptr(),
+                context.len(),
+            )
+        };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        (signature, public_key)
+    }
 
-        let message = b"message";
-        let context = b"";
+    #[test]
+    fn can_sign_and_verify_from_prehash() {
+        let private_key =
+            Vec::from_hex("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42")
+                .unwrap();
+        let prehash = Vec::from_hex(ABC_PREHASH).unwrap();
+        let (signature, public_key) = sign_prehashed(&private_key, &prehash, b"");
+        assert_eq!(signature.to_vec(), Vec::from_hex("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406").unwrap());
+
+        let verify = |prehash: &[u8]| unsafe {
+            std_verify_prehashed(
+                signature.as_ptr(),
+                signature.len(),
+                public_key.as_ptr(),
+                public_key.len(),
+                prehash.as_ptr(),
+                prehash.len(),
+                std::ptr::null(),
+                0,
+            )
+        };
+        assert_eq!(verify(&prehash), ErrorCode::NO_ERROR.value());
+        assert_eq!(verify(&prehash[1..]), ErrorCode::ERROR_CODE_UNKNOWN.value());
+        let mut altered = prehash.clone();
+        altered[0] ^= 1;
         assert_eq!(
-            std_sign(
-                &mut sig,
-                &mut public_key,
-                &private_key,
-                message.as_ptr(),
-                message.len(),
+            verify(&altered),
+            ErrorCode::SIGNATURE_VERIFICATION_FAILURE.value()
+        );
+    }
+
+    #[test]
+    fn batch_verify_from_prehashes() {
+        let context = b"transactions";
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        let mut prehashes = Vec::new();
+        for i in 0..3u8 {
+            let prehash = [i; constants::PREHASH_LENGTH];
+            let (signature, public_key) = sign_prehashed(&[i; 32], &prehash, context);
+            signatures.extend_from_slice(&signature);
+            public_keys.extend_from_slice(&public_key);
+            prehashes.extend_from_slice(&prehash);
+        }
+        let verify = |prehashes: &[u8], out_results: &mut [u8]| unsafe {
+            verify_batch_prehashed(
+                signatures.as_ptr(),
+                signatures.len(),
+                public_keys.as_ptr(),
+                public_keys.len(),
+                prehashes.as_ptr(),
+                prehashes.len(),
                 context.as_ptr(),
                 context.len(),
-            ),
+                std::ptr::null(),
+                3,
+                out_results.as_mut_ptr(),
+                out_results.len(),
+                std::ptr::null_mut(),
+            )
+        };
+
+        let mut results = [0xffu8; 3];
+        assert_eq!(
+            verify(&prehashes, &mut results),
             ErrorCode::NO_ERROR.value()
         );
+        assert_eq!(results, [1, 1, 1]);
 
+        prehashes[constants::PREHASH_LENGTH] ^= 1;
         assert_eq!(
-            std_verify(
-                &sig,
-                &public_key,
-                message.as_ptr(),
-                message.len(),
-                context.as_ptr(),
-                context.len(),
-            ),
-            ErrorCode::NO_ERROR.value()
+            verify(&prehashes, &mut results),
+            ErrorCode::BATCH_VERIFICATION_FAILURE.value()
+        );
+        assert_eq!(results, [1, 0, 1]);
+        assert_eq!(
+            verify(&prehashes[1..], &mut results),
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
         );
     }
 
     #[test]
-    fn batch_verify_validates_multiple_correct_signatures() {
-        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut messages = Vec::new();
-
-        messages.push(b"'Twas brillig, and the slithy toves".to_vec());
-        messages.push(b"Did gyre and gimble in the wabe:".to_vec());
-        messages.push(b"All mimsy were the borogoves,".to_vec());
-        messages.push(b"And the mome raths outgrabe.".to_vec());
-        messages.push(b"'Beware the Jabberwock, my son!".to_vec());
-        let context = b"context";
-
-        for i in 0..messages.len() {
-            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
-            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
-            generate_private_key(&mut private_key);
+    fn batch_verify_arrays_rejects_bad_layout() {
+        let mut batch = FlatBatch::new(&[(b"first", b"one"), (b"second", b"two")]);
+        let mut results = [0u8; 3];
+        assert_eq!(
+            batch.verify(None, Some(&mut results)),
+            ErrorCode::ARRAYS_NOT_EQUAL_LENGTH.value()
+        );
 
-            std_sign(
-                &mut sig,
-                &mut public_key,
-                &private_key,
-                messages[i].as_ptr(),
-                messages[i].len(),
-                context.as_ptr(),
-                context.len(),
+        let offsets = batch.message_offsets.clone();
+        let end = batch.messages.len() as u64;
+        for bad_offsets in [[0, end + 1, end], [1, 5, end], [0, 5, end - 1]].iter() {
+            batch.message_offsets = bad_offsets.to_vec();
+            assert_eq!(
+                batch.verify(None, None),
+                ErrorCode::ERROR_CODE_UNKNOWN.value(),
+                "{:?}",
+                bad_offsets
             );
-            sigs.push(sig.to_vec());
-            public_keys.push(public_key.to_vec());
         }
+        batch.message_offsets = offsets;
+        assert_eq!(batch.verify(None, None), ErrorCode::NO_ERROR.value());
 
-        let mut batch_sigs = SignatureBatch::new();
-        batch_sigs.set_context(b"context".to_vec());
-        batch_sigs.set_messages(RepeatedField::from_vec(messages));
-        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
-        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
+        batch.context_offsets[0] = 1;
+        assert_eq!(
+            batch.verify(None, None),
+            ErrorCode::INVALID_CONTEXT_LENGTH.value()
+        );
+
+        batch.signatures.pop();
+        assert_eq!(
+            batch.verify(None, None),
+            ErrorCode::INVALID_SIGNATURE.value()
+        );
+    }
+
+    #[test]
+    fn batch_verify_each_writes_result_for_every_entry() {
+        let mut batch_sigs = signed_batch(&[b"first", b"second", b"third", b"fourth"]);
+        batch_sigs.mut_messages()[2] = b"altered".to_vec();
         let batch = batch_sigs.write_to_bytes().unwrap();
+        let mut results = [0xffu8; 4];
 
-        let result = verify_batch(batch.as_ptr(), batch.len());
+        let result = unsafe {
+            verify_batch_each(
+                batch.as_ptr(),
+                batch.len(),
+                results.as_mut_ptr(),
+                results.len(),
+                std::ptr::null_mut(),
+            )
+        };
 
-        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        assert_eq!(results, [1, 1, 0, 1]);
     }
 
     #[test]
-    fn batch_verify_fails_on_single_incorrect_message() {
-        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut messages = Vec::new();
+    fn batch_verify_each_fails_for_wrong_results_length() {
+        let batch = signed_batch(&[b"first", b"second"])
+            .write_to_bytes()
+            .unwrap();
+        let mut results = [0u8; 3];
 
-        messages.push(b"'Twas brillig, and the slithy toves".to_vec());
-        messages.push(b"Did gyre and gimble in the wabe:".to_vec());
-        messages.push(b"All mimsy were the borogoves,".to_vec());
-        messages.push(b"And the mome raths outgrabe.".to_vec());
-        messages.push(b"'Beware the Jabberwock, my son!".to_vec());
-        let context = b"context";
+        let result = unsafe {
+            verify_batch_each(
+                batch.as_ptr(),
+                batch.len(),
+                results.as_mut_ptr(),
+                results.len(),
+                std::ptr::null_mut(),
+            )
+        };
 
-        for i in 0..messages.len() {
-            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
-            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
-            generate_private_key(&mut private_key);
+        assert_eq!(result, ErrorCode::ARRAYS_NOT_EQUAL_LENGTH.value());
+    }
 
-            std_sign(
-                &mut sig,
-                &mut public_key,
-                &private_key,
-                messages[i].as_ptr(),
-                messages[i].len(),
-                context.as_ptr(),
-                context.len(),
-            );
-            sigs.push(sig.to_vec());
-            public_keys.push(public_key.to_vec());
-        }
-        //alter a message before batch verification
-        messages[4] = b"The jaws that bite, the claws that catch!".to_vec();
+    #[test]
+    fn strict_policy_rejects_small_order_public_key() {
+        // The identity public key with R = identity and s = 0 satisfies the
+        // verification equation for any message.
+        let mut publickey = [0u8; constants::PUBLIC_KEY_LENGTH];
+        publickey[0] = 1;
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        signature[0] = 1;
+        let message = b"message";
+        let verify = |flags: c_uint| unsafe {
+            std_verify_with_policy(
+                signature.as_ptr(),
+                signature.len(),
+                publickey.as_ptr(),
+                publickey.len(),
+                message.as_ptr(),
+                message.len(),
+                std::ptr::null(),
+                0,
+                SignatureMode::Ed25519 as c_int,
+                flags,
+            )
+        };
 
-        let mut batch_sigs = SignatureBatch::new();
-        batch_sigs.set_context(b"context".to_vec());
-        batch_sigs.set_messages(RepeatedField::from_vec(messages));
-        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
-        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
-        let batch = batch_sigs.write_to_bytes().unwrap();
+        assert_eq!(
+            verify(VerificationPolicy::default().to_flags()),
+            ErrorCode::NO_ERROR.value()
+        );
+        assert_eq!(
+            verify(VerificationPolicy::strict().to_flags()),
+            ErrorCode::INVALID_PUBLIC_KEY.value()
+        );
+        assert_eq!(verify(1 << 31), ErrorCode::ERROR_CODE_UNKNOWN.value());
+    }
 
-        let result = verify_batch(batch.as_ptr(), batch.len());
+    #[test]
+    fn batch_verify_with_strict_policy_accepts_honest_signatures() {
+        let batch = signed_batch(&[b"first", b"second"])
+            .write_to_bytes()
+            .unwrap();
 
+        let result = unsafe {
+            verify_batch_with_policy(
+                batch.as_ptr(),
+                batch.len(),
+                VerificationPolicy::strict().to_flags(),
+                std::ptr::null_mut(),
+            )
+        };
 
-        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
     }
 
     #[test]
-    fn batch_verify_fails_on_single_incorrect_signature() {
-        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut messages = Vec::new();
+    fn can_inspect_identity_public_key() {
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        public_key[0] = 1;
+        let mut report: c_uint = 0;
 
-        messages.push(b"'Twas brillig, and the slithy toves".to_vec());
-        messages.push(b"Did gyre and gimble in the wabe:".to_vec());
-        messages.push(b"All mimsy were the borogoves,".to_vec());
-        messages.push(b"And the mome raths outgrabe.".to_vec());
-        messages.push(b"'Beware the Jabberwock, my son!".to_vec());
-        let context = b"context";
+        let result =
+            unsafe { inspect_public_key(public_key.as_ptr(), public_key.len(), &mut report) };
 
-        for i in 0..messages.len() {
-            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        assert_eq!(
+            report,
+            keys::PUBLIC_KEY_CANONICAL
+                | keys::PUBLIC_KEY_SMALL_ORDER
+                | keys::PUBLIC_KEY_TORSION_FREE
+                | keys::PUBLIC_KEY_IDENTITY
+        );
+    }
+
+    #[test]
+    fn validate_public_key_with_policy_rejects_weak_key() {
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        public_key[0] = 1;
+        let validate = |flags: c_uint| unsafe {
+            validate_public_key_with_policy(public_key.as_ptr(), public_key.len(), flags)
+        };
+
+        assert_eq!(validate(0), ErrorCode::NO_ERROR.value());
+        assert_eq!(
+            validate(policy::REJECT_SMALL_ORDER_KEYS),
+            ErrorCode::INVALID_PUBLIC_KEY.value()
+        );
+    }
+
+    fn seeded_source(seed: &[u8]) -> *mut RandomSource {
+        let mut source = std::ptr::null_mut();
+        let result =
+            unsafe { random_source_insecure_from_seed(&mut source, seed.as_ptr(), seed.len()) };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        source
+    }
+
+    #[test]
+    fn seeded_source_generates_reproducible_keys() {
+        let generate = |seed: u8| {
+            let source = seeded_source(&[seed; rng::SEED_LENGTH]);
             let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
-            generate_private_key(&mut private_key);
+            let result = unsafe {
+                generate_private_key_with_source(
+                    private_key.as_mut_ptr(),
+                    private_key.len(),
+                    source,
+                )
+            };
+            unsafe { random_source_free(source) };
+            assert_eq!(result, ErrorCode::NO_ERROR.value());
+            private_key
+        };
 
-            std_sign(
-                &mut sig,
-                &mut public_key,
-                &private_key,
-                messages[i].as_ptr(),
-                messages[i].len(),
-                context.as_ptr(),
-                context.len(),
-            );
-            sigs.push(sig.to_vec());
-            public_keys.push(public_key.to_vec());
+        assert_eq!(generate(1), generate(1));
+        assert_ne!(generate(1), generate(2));
+
+        let mut source = std::ptr::null_mut();
+        let seed = [0u8; rng::SEED_LENGTH - 1];
+        let result =
+            unsafe { random_source_insecure_from_seed(&mut source, seed.as_ptr(), seed.len()) };
+        assert_eq!(result, ErrorCode::ERROR_CODE_UNKNOWN.value());
+        assert!(source.is_null());
+    }
+
+    unsafe extern "C" fn fill_with_counter(
+        user_data: *mut c_void,
+        out: *mut u8,
+        length: usize,
+    ) -> c_int {
+        let counter = &mut *(user_data as *mut u8);
+        for byte in slice::from_raw_parts_mut(out, length) {
+            *byte = *counter;
+            *counter = counter.wrapping_add(1);
         }
-        //alter a signature before batch verification
-        sigs[3] = sigs[4].to_owned();
+        0
+    }
 
-        let mut batch_sigs = SignatureBatch::new();
-        batch_sigs.set_context(b"context".to_vec());
-        batch_sigs.set_messages(RepeatedField::from_vec(messages));
-        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
-        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
-        let batch = batch_sigs.write_to_bytes().unwrap();
+    unsafe extern "C" fn fail_to_fill(_: *mut c_void, _: *mut u8, _: usize) -> c_int {
+        1
+    }
+
+    #[test]
+    fn callback_source_supplies_randomness() {
+        let mut counter = 0u8;
+        let mut source = std::ptr::null_mut();
+        let result = unsafe {
+            random_source_from_callback(
+                &mut source,
+                Some(fill_with_counter),
+                &mut counter as *mut u8 as *mut c_void,
+            )
+        };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
 
-        let result = verify_batch(batch.as_ptr(), batch.len());
+        let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let result = unsafe {
+            generate_private_key_with_source(private_key.as_mut_ptr(), private_key.len(), source)
+        };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        let expected: Vec<u8> = (0..constants::PRIVATE_KEY_LENGTH as u8).collect();
+        assert_eq!(private_key.to_vec(), expected);
 
-        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        let batch = signed_batch(&[b"first", b"second"])
+            .write_to_bytes()
+            .unwrap();
+        let result = unsafe { verify_batch_with_source(batch.as_ptr(), batch.len(), source) };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        unsafe { random_source_free(source) };
     }
 
     #[test]
-    fn batch_verify_fails_on_incorrect_context() {
-        let mut sigs: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut public_keys: std::vec::Vec<Vec<u8>> = Vec::new();
-        let mut messages = Vec::new();
+    fn failing_callback_source_is_reported() {
+        let mut source = std::ptr::null_mut();
+        let result = unsafe {
+            random_source_from_callback(&mut source, Some(fail_to_fill), std::ptr::null_mut())
+        };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
 
-        messages.push(b"'Twas brillig, and the slithy toves".to_vec());
-        messages.push(b"Did gyre and gimble in the wabe:".to_vec());
-        messages.push(b"All mimsy were the borogoves,".to_vec());
-        messages.push(b"And the mome raths outgrabe.".to_vec());
-        messages.push(b"'Beware the Jabberwock, my son!".to_vec());
-        let context = b"context";
+        let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
+        let result = unsafe {
+            generate_private_key_with_source(private_key.as_mut_ptr(), private_key.len(), source)
+        };
+        unsafe { random_source_free(source) };
+        assert_eq!(result, ErrorCode::ERROR_CODE_UNKNOWN.value());
 
-        for i in 0..messages.len() {
-            let mut sig = [0u8; constants::SIGNATURE_LENGTH];
-            let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
-            let mut private_key = [0u8; constants::PRIVATE_KEY_LENGTH];
-            generate_private_key(&mut private_key);
+        let mut source = std::ptr::null_mut();
+        let result =
+            unsafe { random_source_from_callback(&mut source, None, std::ptr::null_mut()) };
+        assert_eq!(result, ErrorCode::ERROR_CODE_UNKNOWN.value());
+        assert!(source.is_null());
+    }
 
-            std_sign(
-                &mut sig,
-                &mut public_key,
-                &private_key,
-                messages[i].as_ptr(),
-                messages[i].len(),
-                context.as_ptr(),
-                context.len(),
+    #[test]
+    fn seeded_source_reaches_keypair_generation_and_hedged_signing() {
+        let generate_and_sign = |options_flags: c_uint| {
+            let source = seeded_source(&[5u8; rng::SEED_LENGTH]);
+            let mut keypair = std::ptr::null_mut();
+            let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+            let results = unsafe {
+                (
+                    keypair_generate(&mut keypair, source),
+                    keypair_sign_with_options(
+                        keypair,
+                        signature.as_mut_ptr(),
+                        signature.len(),
+                        b"block".as_ptr(),
+                        5,
+                        std::ptr::null(),
+                        0,
+                        options_flags,
+                        source,
+                    ),
+                )
+            };
+            unsafe {
+                keypair_free(keypair);
+                random_source_free(source);
+            }
+            assert_eq!(
+                results,
+                (ErrorCode::NO_ERROR.value(), ErrorCode::NO_ERROR.value())
             );
-            sigs.push(sig.to_vec());
-            public_keys.push(public_key.to_vec());
-        }
+            signature
+        };
 
-        let mut batch_sigs = SignatureBatch::new();
-        batch_sigs.set_context(b"context2".to_vec());
-        batch_sigs.set_messages(RepeatedField::from_vec(messages));
-        batch_sigs.set_signatures(RepeatedField::from_vec(sigs));
-        batch_sigs.set_public_keys(RepeatedField::from_vec(public_keys));
-        let batch = batch_sigs.write_to_bytes().unwrap();
+        let hedged = generate_and_sign(signing::HEDGED_NONCE);
+        assert_eq!(hedged, generate_and_sign(signing::HEDGED_NONCE));
+        assert_ne!(hedged, generate_and_sign(0));
+    }
 
-        let result = verify_batch(batch.as_ptr(), batch.len());
+    #[test]
+    fn failing_source_only_fails_calls_which_draw_from_it() {
+        let mut source = std::ptr::null_mut();
+        let result = unsafe {
+            random_source_from_callback(&mut source, Some(fail_to_fill), std::ptr::null_mut())
+        };
+        assert_eq!(result, ErrorCode::NO_ERROR.value());
+        let private_key = [1u8; constants::PRIVATE_KEY_LENGTH];
+        let mut signature = [0u8; constants::SIGNATURE_LENGTH];
+        let mut public_key = [0u8; constants::PUBLIC_KEY_LENGTH];
+        let sign = |options_flags: c_uint, signature: &mut [u8], public_key: &mut [u8]| unsafe {
+            std_sign_with_options(
+                signature.as_mut_ptr(),
+                signature.len(),
+                public_key.as_mut_ptr(),
+                public_key.len(),
+                private_key.as_ptr(),
+                private_key.len(),
+                b"block".as_ptr(),
+                5,
+                std::ptr::null(),
+                0,
+                SignatureMode::Ed25519ph as c_int,
+                options_flags,
+                source,
+            )
+        };
 
-        assert_eq!(result, ErrorCode::BATCH_VERIFICATION_FAILURE.value());
+        assert_eq!(
+            sign(0, &mut signature, &mut public_key),
+            ErrorCode::NO_ERROR.value()
+        );
+        assert_eq!(
+            sign(signing::HEDGED_NONCE, &mut signature, &mut public_key),
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
+        );
+        let batch = signed_batch(&[b"first"]).write_to_bytes().unwrap();
+        assert_eq!(
+            unsafe { verify_batch_with_policy(batch.as_ptr(), batch.len(), 0, source) },
+            ErrorCode::ERROR_CODE_UNKNOWN.value()
+        );
+        unsafe { random_source_free(source) };
     }
 }